@@ -0,0 +1,86 @@
+//! Reports how many allocations formatting and writing an event takes, which the timings of the
+//! other benches do not show. This is its own bench target because counting allocations slows
+//! down every allocation of the process.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    io::sink,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts allocations so that the number of allocations per event can be reported.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn make_dispatch(current_span: bool, span_list: bool) -> tracing::Dispatch {
+    let collector = json_subscriber::fmt::Subscriber::builder()
+        .with_writer(sink)
+        .with_current_span(current_span)
+        .with_span_list(span_list)
+        .finish();
+    tracing::Dispatch::new(collector)
+}
+
+fn event_with_fields() {
+    tracing::info!(
+        text = black_box("lorem ipsum"),
+        number = black_box(42),
+        float = black_box(4.2),
+        detail = black_box(debug([0, 1, 2])),
+        "hello"
+    );
+}
+
+fn main() {
+    const EVENTS: usize = 1000;
+
+    for (name, current_span, span_list) in [
+        ("no_span_output", false, false),
+        ("current_span", true, false),
+        ("span_list", false, true),
+    ] {
+        tracing::dispatcher::with_default(&make_dispatch(current_span, span_list), || {
+            let _span = tracing::info_span!(
+                "span",
+                n = 1,
+                text = "lorem ipsum",
+                number = 42,
+                float = 4.2,
+                detail = debug([0, 1, 2])
+            )
+            .entered();
+            // The first event fills the thread-local buffer and the per-callsite caches.
+            event_with_fields();
+            let before = ALLOCATIONS.load(Ordering::Relaxed);
+            for _ in 0..EVENTS {
+                event_with_fields();
+            }
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+            println!(
+                "event_with_fields/{name}: {} allocations per event",
+                allocations as f64 / EVENTS as f64,
+            );
+        });
+    }
+}
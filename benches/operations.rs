@@ -31,6 +31,16 @@ fn create_span_with_fields(n: usize) -> tracing::Span {
     )
 }
 
+fn event_with_fields() {
+    tracing::info!(
+        text = black_box("lorem ipsum"),
+        number = black_box(42),
+        float = black_box(4.2),
+        detail = black_box(debug([0, 1, 2])),
+        "hello"
+    );
+}
+
 fn nested_entered_spans(depth: usize) -> Vec<tracing::span::EnteredSpan> {
     let mut guards = Vec::with_capacity(depth);
     for level in 0..depth {
@@ -149,18 +159,43 @@ fn bench_operations(criterion: &mut Criterion) {
             |_| Box::new(()),
             |n| {
                 for _ in 0..n {
-                    tracing::info!(
-                        text = black_box("lorem ipsum"),
-                        number = black_box(42),
-                        float = black_box(4.2),
-                        detail = black_box(debug([0, 1, 2])),
-                        "hello"
-                    );
+                    event_with_fields();
                 }
             },
         ),
     );
 
+    // Level, target, file and line are written from a fragment serialized once per callsite.
+    let callsite_metadata_dispatch = Dispatch::new(
+        json_subscriber::fmt::Subscriber::builder()
+            .with_writer(sink)
+            .with_target(true)
+            .with_file(true)
+            .with_line_number(true)
+            .finish(),
+    );
+    bench_throughput_group(
+        criterion,
+        "event_with_callsite_metadata",
+        [100],
+        [BenchSetup {
+            name: "level_target_file_line",
+            operation: Box::new(move |bencher, &input| {
+                run_bench(
+                    bencher,
+                    input,
+                    &callsite_metadata_dispatch,
+                    |_| Box::new(()),
+                    |n| {
+                        for _ in 0..n {
+                            tracing::info!("hello");
+                        }
+                    },
+                );
+            }),
+        }],
+    );
+
     bench_throughput_group(
         criterion,
         "event_in_span",
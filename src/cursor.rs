@@ -1,7 +1,4 @@
-use std::{
-    cell::{RefCell, RefMut},
-    io,
-};
+use std::{cell::RefCell, io};
 
 pub(crate) struct Cursor<'buf>(RefCell<&'buf mut String>);
 
@@ -25,8 +22,4 @@ impl<'buf> Cursor<'buf> {
     pub fn new(inner: &'buf mut String) -> Self {
         Self(RefCell::new(inner))
     }
-
-    pub fn inner_mut(&self) -> RefMut<'_, &'buf mut String> {
-        self.0.borrow_mut()
-    }
 }
@@ -1,4 +1,7 @@
-use crate::cursor::Cursor;
+use crate::{
+    cursor::Cursor,
+    layer::{escape, write_key},
+};
 
 /// A writer passed to closures registered with
 /// [`add_multiple_dynamic_fields`](crate::JsonLayer::add_multiple_dynamic_fields).
@@ -47,16 +50,11 @@ impl<'a> FieldWriter<'a> {
     ) -> serde_json::Result<()> {
         let rollback = self.writer.len();
 
-        if self.wrote_anything || self.prefix_comma {
-            self.writer.push(',');
-        }
-
-        if let Err(error) = serde_json::to_writer(&Cursor::new(self.writer), key) {
-            self.writer.truncate(rollback);
-            return Err(error);
-        }
-
-        self.writer.push(':');
+        write_key(
+            self.writer,
+            &escape(key),
+            self.wrote_anything || self.prefix_comma,
+        );
 
         if let Err(error) = serde_json::to_writer(&Cursor::new(self.writer), &value) {
             self.writer.truncate(rollback);
@@ -66,4 +64,17 @@ impl<'a> FieldWriter<'a> {
         self.wrote_anything = true;
         Ok(())
     }
+
+    /// Writes fields which were already serialized by another `FieldWriter` without a prefix
+    /// comma.
+    pub(crate) fn write_raw_fields(&mut self, fields: &str) {
+        if fields.is_empty() {
+            return;
+        }
+        if self.wrote_anything || self.prefix_comma {
+            self.writer.push(',');
+        }
+        self.writer.push_str(fields);
+        self.wrote_anything = true;
+    }
 }
@@ -1,41 +1,88 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+
+use tracing_core::Metadata;
 
 #[derive(Debug, Default)]
 pub(crate) struct JsonFieldsInner {
-    pub(crate) fields: BTreeMap<&'static str, serde_json::Value>,
+    pub(crate) fields: BTreeMap<Cow<'static, str>, serde_json::Value>,
     pub(crate) version: usize,
 }
 
-impl JsonFieldsInner {
-    pub(crate) fn finish(self) -> JsonFields {
-        let serialized = serde_json::to_string(&self.fields).unwrap();
-        let serialized = Arc::from(serialized.as_str());
-
-        JsonFields {
-            inner: self,
-            serialized,
-        }
-    }
-}
-
 #[derive(Debug)]
 pub(crate) struct JsonFields {
     pub(crate) inner: JsonFieldsInner,
+    pub(crate) metadata: &'static Metadata<'static>,
+    pub(crate) ids: SpanIds,
+    /// The span as printed by `with_current_span` and `with_span_list`.
     pub(crate) serialized: Arc<str>,
+    /// Only the span's fields which are used when the span is flattened.
+    pub(crate) serialized_fields: Arc<str>,
+}
+
+/// IDs relating the span to other spans, kept with its fields so that they can be printed without
+/// looking up other spans.
+#[derive(Debug)]
+pub(crate) struct SpanIds {
+    pub(crate) id: u64,
+    pub(crate) parent: Option<u64>,
+    pub(crate) follows_from: Vec<u64>,
+}
+
+trait RenameField: Send + Sync {
+    fn rename<'a>(&'a self, name: &'a str) -> &'a str;
 }
 
-impl serde::Serialize for JsonFields {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+struct RenameWithContext<F, T> {
+    renames: F,
+    context: T,
+}
+
+impl<F, T> RenameField for RenameWithContext<F, T>
+where
+    F: for<'a> Fn(&'a str, &'a T) -> &'a str + Send + Sync,
+    T: Send + Sync,
+{
+    fn rename<'a>(&'a self, name: &'a str) -> &'a str {
+        (self.renames)(name, &self.context)
+    }
+}
+
+/// Renames of field names shared by everything in a [`JsonLayer`](crate::JsonLayer) that prints
+/// event or span fields.
+#[derive(Clone)]
+pub(crate) struct FieldRenames(Arc<dyn RenameField>);
+
+impl FieldRenames {
+    pub(crate) fn new<F, T>(renames: F, context: T) -> Self
     where
-        S: serde::Serializer,
+        F: for<'a> Fn(&'a str, &'a T) -> &'a str + Send + Sync + 'static,
+        T: Send + Sync + 'static,
     {
-        use serde::ser::SerializeMap;
-        let mut serializer = serializer.serialize_map(Some(self.inner.fields.len()))?;
+        Self(Arc::new(RenameWithContext { renames, context }))
+    }
+
+    pub(crate) fn rename<'a>(&'a self, name: &'a str) -> &'a str {
+        self.0.rename(name)
+    }
 
-        for (key, value) in &self.inner.fields {
-            serializer.serialize_entry(key, value)?;
+    /// Returns whether both are the same renames, which is also the case if neither renames
+    /// anything.
+    pub(crate) fn same(this: Option<&Self>, other: Option<&Self>) -> bool {
+        match (this, other) {
+            (None, None) => true,
+            (Some(this), Some(other)) => Arc::ptr_eq(&this.0, &other.0),
+            _ => false,
         }
+    }
 
-        serializer.end()
+    /// Renames a field and keeps the original static name if the field was not renamed so that
+    /// nothing is allocated in the common case.
+    pub(crate) fn rename_static(&self, name: &'static str) -> Cow<'static, str> {
+        let renamed = self.rename(name);
+        if renamed == name {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Owned(renamed.to_owned())
+        }
     }
 }
@@ -15,19 +15,30 @@ use tracing_subscriber::{
     Registry,
 };
 
+#[cfg(feature = "tracing-log")]
+use super::names::LOG_MODULE_PATH;
 use super::names::{
+    CRATE_NAME,
+    CRATE_VERSION,
     CURRENT_SPAN,
+    DATADOG_LOGGER_NAME,
+    DATADOG_STATUS,
+    EXECUTABLE,
     FIELDS,
     FILENAME,
+    HOSTNAME,
     LEVEL,
     LINE_NUMBER,
+    PID,
+    SEQUENCE,
     SPAN_LIST,
     TARGET,
     THREAD_ID,
     THREAD_NAME,
     TIMESTAMP,
+    UPTIME,
 };
-use crate::layer::JsonLayer;
+use crate::{layer::JsonLayer, CrateInfo, LevelFormat, SpanFormat, SpanSelection, TimestampFormat};
 
 /// Configures and constructs `Subscriber`s.
 ///
@@ -105,11 +116,25 @@ pub struct SubscriberBuilder<W = fn() -> io::Stdout, T = SystemTime, F = LevelFi
     display_level: bool,
     display_thread_id: bool,
     display_thread_name: bool,
+    display_sequence_number: bool,
+    display_uptime: bool,
+    display_pid: bool,
+    display_hostname: bool,
+    display_executable_name: bool,
+    crate_info: Option<CrateInfo>,
+    env_vars: Vec<(String, String)>,
     display_filename: bool,
     display_line_number: bool,
     flatten_event: bool,
     display_current_span: bool,
     display_span_list: bool,
+    span_selection: SpanSelection,
+    span_format: SpanFormat,
+    timestamp_format: Option<TimestampFormat>,
+    level_format: LevelFormat,
+    datadog: bool,
+    #[cfg(feature = "tracing-log")]
+    display_log_module_path: bool,
     #[cfg(feature = "opentelemetry")]
     display_opentelemetry_ids: bool,
 }
@@ -127,11 +152,25 @@ impl Default for SubscriberBuilder {
             display_level: true,
             display_thread_id: false,
             display_thread_name: false,
+            display_sequence_number: false,
+            display_uptime: false,
+            display_pid: false,
+            display_hostname: false,
+            display_executable_name: false,
+            crate_info: None,
+            env_vars: Vec::new(),
             display_filename: false,
             display_line_number: false,
             flatten_event: false,
             display_current_span: true,
             display_span_list: true,
+            span_selection: SpanSelection::default(),
+            span_format: SpanFormat::default(),
+            timestamp_format: None,
+            level_format: LevelFormat::default(),
+            datadog: false,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: false,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: false,
         }
@@ -153,15 +192,24 @@ where
         let mut layer = JsonLayer::<S>::new(self.make_writer);
 
         if self.display_timestamp {
-            layer.with_timer(TIMESTAMP, self.timer);
+            match self.timestamp_format {
+                Some(format) => layer.with_timestamp(TIMESTAMP, format),
+                None => layer.with_timer(TIMESTAMP, self.timer),
+            };
         }
 
+        let (level_key, target_key) = if self.datadog {
+            (DATADOG_STATUS, DATADOG_LOGGER_NAME)
+        } else {
+            (LEVEL, TARGET)
+        };
+
         if self.display_level {
-            layer.with_level(LEVEL);
+            layer.with_level_format(level_key, self.level_format);
         }
 
         if self.display_target {
-            layer.with_target(TARGET);
+            layer.with_target(target_key);
         }
 
         if self.display_filename {
@@ -180,7 +228,40 @@ where
             layer.with_thread_ids(THREAD_ID);
         }
 
-        if self.flatten_event {
+        if self.display_sequence_number {
+            layer.with_sequence_number(SEQUENCE);
+        }
+
+        if self.display_uptime {
+            layer.with_uptime(UPTIME);
+        }
+
+        if self.display_pid {
+            layer.with_pid(PID);
+        }
+
+        if self.display_hostname {
+            layer.with_hostname(HOSTNAME);
+        }
+
+        if self.display_executable_name {
+            layer.with_executable_name(EXECUTABLE);
+        }
+
+        if let Some(crate_info) = self.crate_info {
+            layer.with_crate_info(CRATE_NAME, CRATE_VERSION, crate_info);
+        }
+
+        for (key, name) in self.env_vars {
+            layer.with_env_var(key, name);
+        }
+
+        #[cfg(feature = "tracing-log")]
+        if self.display_log_module_path {
+            layer.with_log_module_path(LOG_MODULE_PATH);
+        }
+
+        if self.flatten_event || self.datadog {
             layer.with_flattened_event();
         } else {
             layer.with_event(FIELDS);
@@ -191,7 +272,13 @@ where
         }
 
         if self.display_span_list {
-            layer.with_span_list(SPAN_LIST);
+            layer.with_selected_span_list(SPAN_LIST, self.span_selection);
+        }
+
+        layer.with_span_format(self.span_format);
+
+        if self.datadog {
+            layer.with_datadog_service_tags();
         }
 
         (layer, self.filter)
@@ -246,6 +333,23 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
         self
     }
 
+    /// Formats events for [Datadog] the same way as [`JsonLayer::datadog`].
+    ///
+    /// The level is printed as `status`, the target as `logger.name` and event fields including
+    /// `message` are flattened to the top level. The `dd.service`, `dd.env` and `dd.version` are
+    /// read from the `DD_SERVICE`, `DD_ENV` and `DD_VERSION` environment variables and, with one of
+    /// the OpenTelemetry features, `dd.trace_id` and `dd.span_id` are printed. Other options such
+    /// as the span list still apply.
+    ///
+    /// [Datadog]: https://docs.datadoghq.com/logs/log_configuration/attributes_naming_convention/
+    #[must_use]
+    pub fn datadog(self) -> Self {
+        SubscriberBuilder {
+            datadog: true,
+            ..self
+        }
+    }
+
     /// This does nothing. It exists only to mimic `tracing-subscriber`'s API.
     #[deprecated(note = "Calling `with_ansi()` does nothing.")]
     #[must_use]
@@ -279,11 +383,25 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: self.timestamp_format,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
@@ -349,11 +467,25 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: self.timestamp_format,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
@@ -401,11 +533,25 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: self.timestamp_format,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
@@ -440,6 +586,37 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
         }
     }
 
+    /// Sets the formatter to include a list (from root to leaf) of selected currently entered spans
+    /// in formatted events. See [`SpanSelection`] for how spans can be selected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use json_subscriber::SpanSelection;
+    ///
+    /// let subscriber = json_subscriber::fmt()
+    ///     .with_selected_span_list(SpanSelection::new().innermost(5))
+    ///     .finish();
+    /// ```
+    #[must_use]
+    pub fn with_selected_span_list(self, selection: SpanSelection) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            display_span_list: true,
+            span_selection: selection,
+            ..self
+        }
+    }
+
+    /// Sets how spans are represented in the current span and the span list. See [`SpanFormat`] for
+    /// the available options.
+    #[must_use]
+    pub fn with_span_format(self, span_format: SpanFormat) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            span_format,
+            ..self
+        }
+    }
+
     /// Use the given [`timer`] for log message timestamps.
     ///
     /// See the [`tracing_subscriber::fmt::time` module][`time` module] for the
@@ -466,16 +643,44 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: None,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
     }
 
+    /// Use one of the built-in [`TimestampFormat`]s for log message timestamps instead of a
+    /// [`timer`].
+    ///
+    /// Calling [`with_timer`](Self::with_timer) afterwards switches back to the timer.
+    ///
+    /// [`timer`]: FormatTime
+    #[must_use]
+    pub fn with_timestamp_format(self, format: TimestampFormat) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            timestamp_format: Some(format),
+            ..self
+        }
+    }
+
     /// Do not emit timestamps with log messages.
     pub fn without_time(self) -> SubscriberBuilder<W, (), F> {
         SubscriberBuilder {
@@ -488,11 +693,25 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: None,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
@@ -588,6 +807,16 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
         }
     }
 
+    /// Sets how an event's level is displayed, for example as a syslog severity number instead of
+    /// a name.
+    #[must_use]
+    pub fn with_level_format(self, level_format: LevelFormat) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            level_format,
+            ..self
+        }
+    }
+
     /// Sets whether or not the [name] of the current thread is displayed
     /// when formatting events.
     ///
@@ -612,6 +841,99 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
         }
     }
 
+    /// Sets whether or not a sequence number is displayed when formatting events. The number is
+    /// shared by the whole process and incremented for every line which displays it, so it can be
+    /// used to order lines with equal timestamps.
+    #[must_use]
+    pub fn with_sequence_number(self, display_sequence_number: bool) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            display_sequence_number,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the monotonic time elapsed since the subscriber was built is displayed
+    /// when formatting events.
+    #[must_use]
+    pub fn with_uptime(self, display_uptime: bool) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            display_uptime,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the ID of the current process is displayed when formatting events.
+    #[must_use]
+    pub fn with_pid(self, display_pid: bool) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            display_pid,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the hostname of the machine is displayed when formatting events.
+    ///
+    /// See [`JsonLayer::with_hostname`](crate::JsonLayer::with_hostname) for how the hostname is
+    /// found with and without the `hostname` feature.
+    #[must_use]
+    pub fn with_hostname(self, display_hostname: bool) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            display_hostname,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the file name of the current executable is displayed when formatting
+    /// events.
+    #[must_use]
+    pub fn with_executable_name(self, display_executable_name: bool) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            display_executable_name,
+            ..self
+        }
+    }
+
+    /// Displays the name and version of a crate when formatting events. Use the
+    /// [`crate_info!`](crate::crate_info) macro to get the info of your crate.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// json_subscriber::fmt()
+    ///     .with_crate_info(json_subscriber::crate_info!())
+    ///     .init();
+    /// ```
+    #[must_use]
+    pub fn with_crate_info(self, crate_info: CrateInfo) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            crate_info: Some(crate_info),
+            ..self
+        }
+    }
+
+    /// Displays the value of the environment variable `name` under the given key when formatting
+    /// events. Nothing is displayed if the variable is not set when the subscriber is built.
+    #[must_use]
+    pub fn with_env_var(
+        mut self,
+        key: impl Into<String>,
+        name: impl Into<String>,
+    ) -> SubscriberBuilder<W, T, F> {
+        self.env_vars.push((key.into(), name.into()));
+        self
+    }
+
+    /// Sets whether or not the module path of events created from `log` records is displayed.
+    #[cfg(feature = "tracing-log")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tracing-log")))]
+    #[must_use]
+    pub fn with_log_module_path(self, display_log_module_path: bool) -> SubscriberBuilder<W, T, F> {
+        SubscriberBuilder {
+            display_log_module_path,
+            ..self
+        }
+    }
+
     /// Sets whether or not [OpenTelemetry] trace ID and span ID is displayed when formatting
     /// events.
     ///
@@ -688,11 +1010,25 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: self.timestamp_format,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
@@ -741,11 +1077,25 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: self.timestamp_format,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
@@ -801,11 +1151,25 @@ impl<W, T, F> SubscriberBuilder<W, T, F> {
             display_level: self.display_level,
             display_thread_id: self.display_thread_id,
             display_thread_name: self.display_thread_name,
+            display_sequence_number: self.display_sequence_number,
+            display_uptime: self.display_uptime,
+            display_pid: self.display_pid,
+            display_hostname: self.display_hostname,
+            display_executable_name: self.display_executable_name,
+            crate_info: self.crate_info,
+            env_vars: self.env_vars,
             display_filename: self.display_filename,
             display_line_number: self.display_line_number,
             flatten_event: self.flatten_event,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            span_selection: self.span_selection,
+            span_format: self.span_format,
+            timestamp_format: self.timestamp_format,
+            level_format: self.level_format,
+            datadog: self.datadog,
+            #[cfg(feature = "tracing-log")]
+            display_log_module_path: self.display_log_module_path,
             #[cfg(feature = "opentelemetry")]
             display_opentelemetry_ids: self.display_opentelemetry_ids,
         }
@@ -834,6 +1198,7 @@ mod tests {
     use crate::{
         layer::JsonLayer,
         tests::{MockMakeWriter, MockTime},
+        LevelFormat,
     };
 
     fn subscriber() -> SubscriberBuilder {
@@ -937,6 +1302,29 @@ mod tests {
         });
     }
 
+    #[test]
+    fn json_level_format() {
+        #[rustfmt::skip]
+        let expected = "{\"timestamp\":\"fake time\",\"level\":40,\"target\":\"json_subscriber::fmt::builder::tests\",\"fields\":{\"message\":\"some json test\"}}\n";
+        let collector = subscriber().with_level_format(LevelFormat::Bunyan);
+        test_json(expected, collector, || {
+            tracing::warn!("some json test");
+        });
+    }
+
+    #[test]
+    fn json_datadog() {
+        #[rustfmt::skip]
+        let expected = "{\"timestamp\":\"fake time\",\"status\":\"INFO\",\"logger.name\":\"json_subscriber::fmt::builder::tests\",\"message\":\"some json test\"}\n";
+        let collector = subscriber()
+            .datadog()
+            .with_current_span(false)
+            .with_span_list(false);
+        test_json(expected, collector, || {
+            tracing::info!("some json test");
+        });
+    }
+
     #[test]
     fn json_disabled_span_list_event() {
         #[rustfmt::skip]
@@ -12,19 +12,29 @@ use tracing_subscriber::{
     Registry,
 };
 
+#[cfg(feature = "tracing-log")]
+use super::names::LOG_MODULE_PATH;
 use super::names::{
     CURRENT_SPAN,
     FIELDS,
     FILENAME,
     LEVEL,
     LINE_NUMBER,
+    SEQUENCE,
     SPAN_LIST,
     TARGET,
     THREAD_ID,
     THREAD_NAME,
     TIMESTAMP,
+    UPTIME,
+};
+use crate::{
+    layer::{FlatSchemaKey, JsonLayer},
+    LevelFormat,
+    SpanFormat,
+    SpanSelection,
+    TimestampFormat,
 };
-use crate::layer::{FlatSchemaKey, JsonLayer};
 
 /// A [`Layer`] that logs JSON formatted representations of `tracing` events.
 ///
@@ -417,6 +427,17 @@ where
         self
     }
 
+    /// Sets the formatter to include a list (from root to leaf) of selected currently entered spans
+    /// in formatted events. See [`SpanSelection`] for how spans can be selected.
+    ///
+    /// This overrides any previous calls to [`with_span_list`](Self::with_span_list) and
+    /// [`with_flat_span_list`](Self::with_flat_span_list).
+    #[must_use]
+    pub fn with_selected_span_list(mut self, selection: SpanSelection) -> Self {
+        self.inner.with_selected_span_list(SPAN_LIST, selection);
+        self
+    }
+
     /// Sets whether or not the formatter will include an object containing all parent spans'
     /// fields. If multiple ancestor spans recorded the same field, the span closer to the leaf span
     /// overrides the values of spans that are closer to the root spans.
@@ -432,6 +453,14 @@ where
         self
     }
 
+    /// Sets how spans are represented in the current span and the span list. See [`SpanFormat`] for
+    /// the available options.
+    #[must_use]
+    pub fn with_span_format(mut self, span_format: SpanFormat) -> Self {
+        self.inner.with_span_format(span_format);
+        self
+    }
+
     /// Use the given [`timer`] for log message timestamps.
     ///
     /// See the [`time` module] for the provided timer implementations.
@@ -452,6 +481,14 @@ where
         self
     }
 
+    /// Use one of the built-in [`TimestampFormat`]s for log message timestamps instead of a
+    /// [`timer`](Self::with_timer).
+    #[must_use]
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.inner.with_timestamp(TIMESTAMP, format);
+        self
+    }
+
     /// Do not emit timestamps with log messages.
     #[must_use]
     pub fn without_time(mut self) -> Self {
@@ -510,6 +547,14 @@ where
         self
     }
 
+    /// Sets how an event's level is displayed, for example as a syslog severity number instead of
+    /// a name. This also enables displaying the level.
+    #[must_use]
+    pub fn with_level_format(mut self, format: LevelFormat) -> Self {
+        self.inner.with_level_format(LEVEL, format);
+        self
+    }
+
     /// Sets whether or not the [name] of the current thread is displayed
     /// when formatting events.
     ///
@@ -539,6 +584,43 @@ where
         self
     }
 
+    /// Sets whether or not a sequence number is displayed when formatting events. The number is
+    /// shared by the whole process and incremented for every line which displays it.
+    #[must_use]
+    pub fn with_sequence_number(mut self, display_sequence_number: bool) -> Self {
+        if display_sequence_number {
+            self.inner.with_sequence_number(SEQUENCE);
+        } else {
+            self.inner.remove_field(SEQUENCE);
+        }
+        self
+    }
+
+    /// Sets whether or not the monotonic time elapsed since this layer was configured is displayed
+    /// when formatting events.
+    #[must_use]
+    pub fn with_uptime(mut self, display_uptime: bool) -> Self {
+        if display_uptime {
+            self.inner.with_uptime(UPTIME);
+        } else {
+            self.inner.remove_field(UPTIME);
+        }
+        self
+    }
+
+    /// Sets whether or not the module path of events created from `log` records is displayed.
+    #[cfg(feature = "tracing-log")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tracing-log")))]
+    #[must_use]
+    pub fn with_log_module_path(mut self, display_log_module_path: bool) -> Self {
+        if display_log_module_path {
+            self.inner.with_log_module_path(LOG_MODULE_PATH);
+        } else {
+            self.inner.remove_field(LOG_MODULE_PATH);
+        }
+        self
+    }
+
     /// Sets whether or not [OpenTelemetry] trace ID and span ID is displayed when formatting
     /// events.
     ///
@@ -559,7 +641,10 @@ mod tests {
     use tracing_subscriber::{registry, Layer as _, Registry};
 
     use super::Layer;
-    use crate::tests::{MockMakeWriter, MockTime};
+    use crate::{
+        tests::{MockMakeWriter, MockTime},
+        SpanFormat,
+    };
 
     fn test_json<W, T>(
         expected: &serde_json::Value,
@@ -661,7 +746,7 @@ mod tests {
 
         // Notice that there is `level` twice so this is not a valid JSON.
         #[rustfmt::skip]
-        let expected = "{\"level\":\"INFO\",\"timestamp\":\"fake time\",\"level\":\"this is a bug\",\"message\":\"some json test\"}\n";
+        let expected = "{\"level\":\"INFO\",\"timestamp\":\"fake time\",\"message\":\"some json test\",\"level\":\"this is a bug\"}\n";
 
         let layer = Layer::default()
             .flatten_event(true)
@@ -775,6 +860,97 @@ mod tests {
         });
     }
 
+    #[test]
+    fn nested_span_format() {
+        let expected = json!(
+            {
+                "timestamp": "fake time",
+                "level": "INFO",
+                "span": {
+                    "name": "child_span",
+                    "target": "json_subscriber::fmt::layer::tests",
+                    "level": "DEBUG",
+                    "fields": {
+                        "name": "user field",
+                        "number": 100,
+                    },
+                },
+                "spans": [
+                    {
+                        "name": "json_span",
+                        "target": "json_subscriber::fmt::layer::tests",
+                        "level": "INFO",
+                        "fields": {
+                            "answer": 42,
+                        },
+                    },
+                    {
+                        "name": "child_span",
+                        "target": "json_subscriber::fmt::layer::tests",
+                        "level": "DEBUG",
+                        "fields": {
+                            "name": "user field",
+                            "number": 100,
+                        },
+                    },
+                ],
+                "fields": {
+                    "message": "some json test",
+                },
+            }
+        );
+
+        let layer = Layer::default()
+            .with_target(false)
+            .with_span_format(SpanFormat::nested().with_target(true).with_level(true));
+
+        test_json(&expected, layer, || {
+            let _guard = tracing::info_span!("json_span", answer = 42).entered();
+            let _guard =
+                tracing::debug_span!("child_span", number = 100, name = "user field").entered();
+            tracing::info!("some json test");
+        });
+    }
+
+    #[test]
+    fn flat_span_name_wins() {
+        let expected = json!(
+            {
+                "timestamp": "fake time",
+                "span": {
+                    "name": "json_span",
+                    "id": 0,
+                    "line": 0,
+                },
+                "fields": {
+                    "message": "some json test",
+                },
+            }
+        );
+
+        let layer = Layer::default()
+            .with_target(false)
+            .with_level(false)
+            .with_span_list(false)
+            .with_span_format(SpanFormat::flat().with_id(true).with_line_number(true));
+
+        let actual = produce_log_line(layer, || {
+            let span =
+                tracing::info_span!("json_span", name = "user field", id = tracing::field::Empty);
+            span.record("name", "recorded field");
+            let _guard = span.entered();
+            tracing::info!("some json test");
+        });
+        let mut actual = serde_json::from_str::<serde_json::Value>(&actual).unwrap();
+        // The line number and the ID are not stable so we just check they are there.
+        for key in ["id", "line"] {
+            let value = &mut actual["span"][key];
+            assert!(value.is_u64());
+            *value = json!(0);
+        }
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn target_quote() {
         let expected = json!(
@@ -1,10 +1,21 @@
+pub(super) const CRATE_NAME: &str = "crateName";
+pub(super) const CRATE_VERSION: &str = "crateVersion";
 pub(super) const CURRENT_SPAN: &str = "span";
+pub(super) const DATADOG_LOGGER_NAME: &str = "logger.name";
+pub(super) const DATADOG_STATUS: &str = "status";
+pub(super) const EXECUTABLE: &str = "executable";
 pub(super) const FIELDS: &str = "fields";
 pub(super) const FILENAME: &str = "filename";
+pub(super) const HOSTNAME: &str = "hostname";
 pub(super) const LEVEL: &str = "level";
 pub(super) const LINE_NUMBER: &str = "line_number";
+#[cfg(feature = "tracing-log")]
+pub(super) const LOG_MODULE_PATH: &str = "log.module_path";
+pub(super) const PID: &str = "pid";
+pub(super) const SEQUENCE: &str = "sequence";
 pub(super) const SPAN_LIST: &str = "spans";
 pub(super) const TARGET: &str = "target";
 pub(super) const THREAD_ID: &str = "threadId";
 pub(super) const THREAD_NAME: &str = "threadName";
 pub(super) const TIMESTAMP: &str = "timestamp";
+pub(super) const UPTIME: &str = "uptime";
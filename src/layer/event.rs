@@ -1,7 +1,7 @@
-use std::{borrow::Cow, fmt, ops::Deref};
+use std::{borrow::Cow, cell::RefCell, fmt, ops::Deref, sync::PoisonError};
 
-use serde::{ser::SerializeMap, Serializer};
-use tracing::{dispatcher::WeakDispatch, Event, Metadata, Subscriber};
+use serde::Serialize;
+use tracing::{callsite::Identifier, dispatcher::WeakDispatch, Event, Metadata, Subscriber};
 #[cfg(feature = "tracing-log")]
 use tracing_log::NormalizeEvent;
 use tracing_subscriber::{
@@ -13,7 +13,8 @@ use crate::{
     cached::Cached,
     cursor::Cursor,
     field_writer::FieldWriter,
-    layer::{JsonLayer, JsonValue, SchemaKey},
+    fields::FieldRenames,
+    layer::{escape, write_event_fields, write_key, JsonLayer, JsonValue, SchemaKey},
     serde::JsonSubscriberFormatter,
 };
 
@@ -22,6 +23,11 @@ pub struct EventRef<'a, 'b, 'c, R: for<'lookup> LookupSpan<'lookup>> {
     context: &'a Context<'b, R>,
     event: &'a Event<'b>,
     span: Option<SpanRef<'c, R>>,
+    field_renames: Option<&'a FieldRenames>,
+    /// The event's fields serialized with each of the used renames, kept only if the event is
+    /// written by multiple schemas.
+    #[allow(clippy::type_complexity)]
+    serialized_fields: Option<RefCell<Vec<(Option<FieldRenames>, String)>>>,
 }
 
 impl<'a, R: for<'lookup> LookupSpan<'lookup>> Deref for EventRef<'a, '_, '_, R> {
@@ -32,7 +38,58 @@ impl<'a, R: for<'lookup> LookupSpan<'lookup>> Deref for EventRef<'a, '_, '_, R>
     }
 }
 
-impl<'c, R: Subscriber + for<'lookup> LookupSpan<'lookup>> EventRef<'_, '_, 'c, R> {
+impl<'a, 'b, R: Subscriber + for<'lookup> LookupSpan<'lookup>> EventRef<'a, 'b, 'a, R> {
+    pub(super) fn new(
+        context: &'a Context<'b, R>,
+        event: &'a Event<'b>,
+        field_renames: Option<&'a FieldRenames>,
+        share_fields: bool,
+    ) -> Self {
+        Self {
+            context,
+            event,
+            span: context.event_span(event),
+            field_renames,
+            serialized_fields: share_fields.then(RefCell::default),
+        }
+    }
+}
+
+impl<'a, 'c, R: Subscriber + for<'lookup> LookupSpan<'lookup>> EventRef<'a, '_, 'c, R> {
+    /// Sets the field renames used for the event's fields, which differ between schemas.
+    pub(super) fn set_field_renames(&mut self, field_renames: Option<&'a FieldRenames>) {
+        self.field_renames = field_renames;
+    }
+
+    /// Writes the event's fields renamed with `renames`. If the event is shared by multiple
+    /// schemas, the fields are visited only once for each renames and then copied.
+    pub(crate) fn write_fields(
+        &self,
+        renames: Option<&FieldRenames>,
+        writer: &mut FieldWriter<'_>,
+    ) {
+        let Some(serialized_fields) = &self.serialized_fields else {
+            write_event_fields(self.event, renames, writer);
+            return;
+        };
+
+        let mut serialized_fields = serialized_fields.borrow_mut();
+        let index = serialized_fields
+            .iter()
+            .position(|(cached, _)| FieldRenames::same(cached.as_ref(), renames))
+            .unwrap_or_else(|| {
+                let mut fields = String::new();
+                write_event_fields(
+                    self.event,
+                    renames,
+                    &mut FieldWriter::new(&mut fields, false),
+                );
+                serialized_fields.push((renames.cloned(), fields));
+                serialized_fields.len() - 1
+            });
+        writer.write_raw_fields(&serialized_fields[index].1);
+    }
+
     /// Returns the span's name,
     #[allow(dead_code)]
     pub fn name(&self) -> &'static str {
@@ -40,7 +97,6 @@ impl<'c, R: Subscriber + for<'lookup> LookupSpan<'lookup>> EventRef<'_, '_, 'c,
     }
 
     #[cfg(feature = "tracing-log")]
-    #[allow(dead_code)]
     pub fn normalized_metadata(&self) -> Option<Metadata<'_>> {
         self.event.normalized_metadata()
     }
@@ -62,6 +118,10 @@ impl<'c, R: Subscriber + for<'lookup> LookupSpan<'lookup>> EventRef<'_, '_, 'c,
     pub(super) fn context(&self) -> &Context<'_, R> {
         self.context
     }
+
+    pub(super) fn field_renames(&self) -> Option<&FieldRenames> {
+        self.field_renames
+    }
 }
 
 impl<S, W> JsonLayer<S, W>
@@ -70,216 +130,209 @@ where
 {
     pub(crate) fn format_event(
         &self,
-        context: &Context<'_, S>,
+        event_ref: &EventRef<'_, '_, '_, S>,
+        dispatch: Option<&WeakDispatch>,
         writer: &mut String,
-        event: &Event<'_>,
     ) -> fmt::Result {
-        let mut visit = || {
-            let writer = Cursor::new(writer);
-            let mut serializer =
-                serde_json::Serializer::with_formatter(&writer, JsonSubscriberFormatter);
+        self.write_resource_logs_prefix(writer, event_ref.event())?;
 
-            let mut serializer = serializer.serialize_map(None)?;
+        writer.push('{');
+        let mut serialized_anything = false;
 
-            let span = context.event_span(event);
+        let static_runs = self
+            .has_static_values()
+            .then(|| self.static_runs(event_ref));
+        let mut run_index = 0;
 
-            let event_ref = EventRef {
-                context,
-                event,
-                span,
-            };
-
-            let mut serialized_anything = false;
-            let mut serialized_anything_serde = false;
+        let mut keyed_values = self.keyed_values.iter().peekable();
+        while let Some((SchemaKey::Static(key), value)) = keyed_values.next() {
+            if let JsonValue::DynamicFromEventWithWriter(fun) = value {
+                write_key(writer, key, serialized_anything);
+                writer.push('{');
+                fun(event_ref, &mut FieldWriter::new(writer, false));
+                writer.push('}');
+                serialized_anything = true;
+                continue;
+            }
 
-            for (SchemaKey::Static(key), value) in &self.keyed_values {
-                let Some(value) = resolve_json_value(value, &event_ref, self.dispatch.get()) else {
-                    continue;
+            if let JsonValue::StaticFromMetadata(_) = value {
+                // The run of adjacent values only depending on the callsite is written at once.
+                while keyed_values
+                    .next_if(|(_, value)| matches!(value, JsonValue::StaticFromMetadata(_)))
+                    .is_some()
+                {}
+                let written = match &static_runs {
+                    Some(StaticRuns::Cached(callsite)) => {
+                        let callsite_values = self
+                            .callsite_values
+                            .read()
+                            .unwrap_or_else(PoisonError::into_inner);
+                        callsite_values
+                            .get(callsite)
+                            .and_then(|runs| runs.get(run_index))
+                            .is_some_and(|run| push_run(writer, run, serialized_anything))
+                    },
+                    Some(StaticRuns::Uncached(runs)) => {
+                        push_run(writer, &runs[run_index], serialized_anything)
+                    },
+                    None => false,
                 };
+                run_index += 1;
+                serialized_anything |= written;
+                continue;
+            }
 
-                match value {
-                    MaybeCached::Serde(value) => {
-                        if serialized_anything && !serialized_anything_serde {
-                            writer.inner_mut().push(',');
-                        }
-                        serialized_anything = true;
-                        serialized_anything_serde = true;
-                        serializer.serialize_entry(key, &value)?;
-                    },
-                    MaybeCached::Cached(Cached::Raw(raw)) => {
+            let Some(value) = resolve_json_value(value, event_ref, dispatch) else {
+                continue;
+            };
+
+            let rollback_position = writer.len();
+            write_key(writer, key, serialized_anything);
+            match value {
+                MaybeCached::Serde(value) => {
+                    write_serde(writer, &*value)?;
+                    serialized_anything = true;
+                },
+                MaybeCached::Cached(Cached::Raw(raw)) => {
+                    debug_assert!(
+                        serde_json::to_value(&*raw).is_ok(),
+                        "[json-subscriber] provided cached value is not valid json: {raw}",
+                    );
+                    writer.push_str(&raw);
+                    serialized_anything = true;
+                },
+                MaybeCached::Cached(Cached::Array(arr)) => {
+                    writer.push('[');
+                    let mut first = true;
+                    for raw in arr {
                         debug_assert!(
                             serde_json::to_value(&*raw).is_ok(),
-                            "[json-subscriber] provided cached value is not valid json: {raw}",
+                            "[json-subscriber] provided cached value in array is not valid json: \
+                             {raw}",
                         );
-                        let mut writer = writer.inner_mut();
-                        if serialized_anything {
+                        if !first {
                             writer.push(',');
                         }
-                        serialized_anything = true;
-                        writer.push('"');
-                        writer.push_str(key);
-                        writer.push_str("\":");
+                        first = false;
                         writer.push_str(&raw);
-                    },
-                    MaybeCached::Cached(Cached::Array(arr)) => {
-                        let mut writer = writer.inner_mut();
-                        if serialized_anything {
-                            writer.push(',');
-                        }
-                        serialized_anything = true;
-                        writer.push('"');
-                        writer.push_str(key);
-                        writer.push_str("\":[");
-                        let mut first = true;
-                        for raw in arr {
+                    }
+                    writer.push(']');
+                    serialized_anything = true;
+                },
+                MaybeCached::Raw(raw_fun) => {
+                    let start_position = writer.len();
+                    match raw_fun(event_ref, writer) {
+                        Ok(()) => {
                             debug_assert!(
-                                serde_json::to_value(&*raw).is_ok(),
-                                "[json-subscriber] provided cached value in array is not valid \
-                                 json: {raw}",
+                                serde_json::to_value(&writer[start_position..]).is_ok(),
+                                "[json-subscriber] raw value factory created invalid json: {}",
+                                &writer[start_position..],
                             );
-                            if !first {
-                                writer.push(',');
-                            }
-                            first = false;
-                            writer.push_str(&raw);
-                        }
-                        writer.push(']');
-                    },
-                    MaybeCached::Raw(raw_fun) => {
-                        let mut writer = writer.inner_mut();
-                        let rollback_position = writer.len();
-                        if serialized_anything {
-                            writer.push(',');
-                        }
-                        writer.push('"');
-                        writer.push_str(key);
-                        writer.push_str("\":");
-                        let start_position = writer.len();
-                        match raw_fun(&event_ref, &mut *writer) {
-                            Ok(()) => {
-                                debug_assert!(
-                                    serde_json::to_value(&writer[start_position..]).is_ok(),
-                                    "[json-subscriber] raw value factory created invalid json: {}",
-                                    &writer[start_position..],
-                                );
-                                serialized_anything = true;
-                            },
-                            Err(error) => {
-                                eprintln!(
-                                    "[json-subscriber] unable to format raw value to string: \
-                                     {error}"
-                                );
-                                writer.truncate(rollback_position);
-                            },
-                        }
-                    },
-                }
+                            serialized_anything = true;
+                        },
+                        Err(error) => {
+                            eprintln!(
+                                "[json-subscriber] unable to format raw value to string: {error}"
+                            );
+                            writer.truncate(rollback_position);
+                        },
+                    }
+                },
             }
+        }
 
-            for value in self.flattened_values.values() {
-                if let JsonValue::DynamicFromEventWithWriter(fun) = value {
-                    let mut inner = writer.inner_mut();
-                    let mut field_writer = FieldWriter::new(&mut inner, serialized_anything);
-                    fun(&event_ref, &mut field_writer);
-                    if field_writer.wrote_anything() {
-                        serialized_anything = true;
-                    }
-                    continue;
+        for value in self.flattened_values.values() {
+            if let JsonValue::DynamicFromEventWithWriter(fun) = value {
+                let mut field_writer = FieldWriter::new(writer, serialized_anything);
+                fun(event_ref, &mut field_writer);
+                if field_writer.wrote_anything() {
+                    serialized_anything = true;
                 }
+                continue;
+            }
 
-                let Some(value) = resolve_json_value(value, &event_ref, self.dispatch.get()) else {
-                    continue;
-                };
+            let Some(value) = resolve_json_value(value, event_ref, dispatch) else {
+                continue;
+            };
 
-                match value {
-                    MaybeCached::Serde(value) => {
-                        let map = value.as_object().unwrap();
-                        if !map.is_empty() {
-                            if serialized_anything && !serialized_anything_serde {
-                                writer.inner_mut().push(',');
-                            }
-                            serialized_anything = true;
-                            serialized_anything_serde = true;
-                            for (key, value) in map {
-                                serializer.serialize_entry(key, value)?;
-                            }
-                        }
-                    },
-                    MaybeCached::Cached(Cached::Raw(raw)) => {
-                        debug_assert!(
-                            serde_json::to_value(&*raw).is_ok(),
-                            "[json-subscriber] provided cached value is not valid json: {raw}",
+            match value {
+                MaybeCached::Serde(value) => {
+                    for (key, value) in value.as_object().unwrap() {
+                        write_key(writer, &escape(key), serialized_anything);
+                        write_serde(writer, value)?;
+                        serialized_anything = true;
+                    }
+                },
+                MaybeCached::Cached(Cached::Raw(raw)) => {
+                    debug_assert!(
+                        serde_json::to_value(&*raw).is_ok(),
+                        "[json-subscriber] provided cached value is not valid json: {raw}",
+                    );
+                    if !raw.contains('\"') {
+                        // If the raw string contains at least a single quote, there is at least
+                        // one field in the object. Otherwise it is empty and we just skip it.
+                        // Assuming it's a valid JSON of course.
+                        continue;
+                    }
+                    let Some(object_contents) = raw
+                        .as_ref()
+                        .trim()
+                        .strip_prefix('{')
+                        .and_then(|str| str.strip_suffix('}'))
+                    else {
+                        eprintln!(
+                            "[json-subscriber] provided cached value cannot be flattened because \
+                             it is not an object: {raw}"
                         );
-                        if !raw.contains('\"') {
-                            // If the raw string contains at least a single quote, there is at least
-                            // one field in the object. Otherwise it is empty and we just skip it.
-                            // Assuming it's a valid JSON of course.
-                            continue;
-                        }
-                        let Some(object_contents) = raw
-                            .as_ref()
-                            .trim()
-                            .strip_prefix('{')
-                            .and_then(|str| str.strip_suffix('}'))
-                        else {
-                            eprintln!(
-                                "[json-subscriber] provided cached value cannot be flattened \
-                                 because it is not an object: {raw}"
+                        continue;
+                    };
+                    if serialized_anything {
+                        writer.push(',');
+                    }
+                    serialized_anything = true;
+                    writer.push_str(object_contents);
+                },
+                MaybeCached::Cached(Cached::Array(_arr)) => {
+                    todo!();
+                },
+                MaybeCached::Raw(raw_fun) => {
+                    let mut output = String::new();
+                    match raw_fun(event_ref, &mut output) {
+                        Ok(()) => {
+                            debug_assert!(
+                                serde_json::to_value(&output).is_ok(),
+                                "[json-subscriber] raw value factory created invalid json: \
+                                 {output}",
                             );
-                            continue;
-                        };
-                        let mut writer = writer.inner_mut();
-                        if serialized_anything {
-                            writer.push(',');
-                        }
-                        serialized_anything = true;
-                        writer.push_str(object_contents);
-                    },
-                    MaybeCached::Cached(Cached::Array(_arr)) => {
-                        todo!();
-                    },
-                    MaybeCached::Raw(raw_fun) => {
-                        let mut output = String::new();
-                        match raw_fun(&event_ref, &mut output) {
-                            Ok(()) => {
-                                debug_assert!(
-                                    serde_json::to_value(&output).is_ok(),
-                                    "[json-subscriber] raw value factory created invalid json: \
-                                     {output}",
-                                );
-                                let Some(object_contents) = output
-                                    .trim()
-                                    .strip_prefix('{')
-                                    .and_then(|str| str.strip_suffix('}'))
-                                else {
-                                    eprintln!(
-                                        "[json-subscriber] provided cached value cannot be \
-                                         flattened because it is not an object: {output}"
-                                    );
-                                    continue;
-                                };
-                                let mut writer = writer.inner_mut();
-                                if serialized_anything {
-                                    writer.push(',');
-                                }
-                                serialized_anything = true;
-                                writer.push_str(object_contents);
-                            },
-                            Err(error) => {
+                            let Some(object_contents) = output
+                                .trim()
+                                .strip_prefix('{')
+                                .and_then(|str| str.strip_suffix('}'))
+                            else {
                                 eprintln!(
-                                    "[json-subscriber] unable to format raw value to string: \
-                                     {error}"
+                                    "[json-subscriber] provided cached value cannot be flattened \
+                                     because it is not an object: {output}"
                                 );
-                            },
-                        }
-                    },
-                }
+                                continue;
+                            };
+                            if serialized_anything {
+                                writer.push(',');
+                            }
+                            serialized_anything = true;
+                            writer.push_str(object_contents);
+                        },
+                        Err(error) => {
+                            eprintln!(
+                                "[json-subscriber] unable to format raw value to string: {error}"
+                            );
+                        },
+                    }
+                },
             }
+        }
 
-            serializer.end()
-        };
-
-        visit().map_err(|_| fmt::Error)?;
+        writer.push('}');
+        self.write_resource_logs_suffix(writer);
         writer.push('\n');
 
         debug_assert!(
@@ -289,6 +342,135 @@ where
 
         Ok(())
     }
+
+    /// Serializes the values which only depend on the callsite of `metadata` and caches them so
+    /// that events from this callsite only copy them.
+    pub(super) fn cache_callsite_values(&self, metadata: &'static Metadata<'static>) {
+        if !self.has_static_values() {
+            return;
+        }
+        self.callsite_values
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(metadata.callsite(), self.serialize_static_runs(metadata));
+    }
+
+    fn has_static_values(&self) -> bool {
+        *self.has_static_values.get_or_init(|| {
+            self.keyed_values
+                .values()
+                .any(|value| matches!(value, JsonValue::StaticFromMetadata(_)))
+        })
+    }
+
+    /// Returns where to find the serialized values which only depend on the event's callsite,
+    /// serializing them if they are not cached yet.
+    fn static_runs(&self, event_ref: &EventRef<'_, '_, '_, S>) -> StaticRuns {
+        // Events created from `log` records all share the same callsite, so their values cannot be
+        // cached.
+        #[cfg(feature = "tracing-log")]
+        if let Some(metadata) = event_ref.normalized_metadata() {
+            return StaticRuns::Uncached(self.serialize_static_runs(&metadata));
+        }
+
+        let metadata = event_ref.metadata();
+        let callsite = metadata.callsite();
+        let cached = self
+            .callsite_values
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(&callsite);
+        if !cached {
+            // The callsite was registered before the schema changed, or by another dispatcher.
+            let runs = self.serialize_static_runs(metadata);
+            self.callsite_values
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(callsite.clone(), runs);
+        }
+        StaticRuns::Cached(callsite)
+    }
+
+    /// Serializes the values which only depend on the callsite as `"key":value` entries, joining
+    /// each run of adjacent keyed values into one string. A run is empty if none of its values
+    /// could be serialized.
+    fn serialize_static_runs(&self, metadata: &Metadata<'_>) -> Box<[Box<str>]> {
+        let mut runs = Vec::new();
+        let mut run = None::<String>;
+        for (SchemaKey::Static(key), value) in &self.keyed_values {
+            let JsonValue::StaticFromMetadata(fun) = value else {
+                runs.extend(run.take().map(String::into_boxed_str));
+                continue;
+            };
+            let run = run.get_or_insert_with(String::new);
+            let rollback_position = run.len();
+            write_key(run, key, !run.is_empty());
+            if !write_static_value(fun, metadata, run) {
+                run.truncate(rollback_position);
+            }
+        }
+        runs.extend(run.map(String::into_boxed_str));
+        runs.into_boxed_slice()
+    }
+}
+
+/// Where to find the serialized values of an event which only depend on its callsite.
+enum StaticRuns {
+    /// The values are cached for the callsite in [`JsonLayer::callsite_values`]. They are looked
+    /// up for each run instead of holding the lock while formatting, since other values may emit
+    /// events which register new callsites.
+    Cached(Identifier),
+    /// The values serialized for an event normalized from a `log` record.
+    #[cfg_attr(not(feature = "tracing-log"), allow(dead_code))]
+    Uncached(Box<[Box<str>]>),
+}
+
+/// Writes a run of serialized `"key":value` entries and returns whether it wrote anything.
+fn push_run(writer: &mut String, run: &str, prefix_comma: bool) -> bool {
+    if run.is_empty() {
+        return false;
+    }
+    if prefix_comma {
+        writer.push(',');
+    }
+    writer.push_str(run);
+    true
+}
+
+/// Writes a value which only depends on the callsite and returns whether it succeeded. Nothing is
+/// written if it did not.
+fn write_static_value(
+    fun: &(dyn Fn(&Metadata<'_>, &mut dyn fmt::Write) -> fmt::Result + Send + Sync),
+    metadata: &Metadata<'_>,
+    writer: &mut String,
+) -> bool {
+    let value_position = writer.len();
+    match fun(metadata, writer) {
+        Ok(()) => {
+            debug_assert!(
+                serde_json::from_str::<serde_json::Value>(&writer[value_position..]).is_ok(),
+                "[json-subscriber] raw value factory created invalid json: {}",
+                &writer[value_position..],
+            );
+            true
+        },
+        Err(error) => {
+            eprintln!("[json-subscriber] unable to format raw value to string: {error}");
+            writer.truncate(value_position);
+            false
+        },
+    }
+}
+
+/// Serializes `value` with the formatter used for the whole line.
+fn write_serde(writer: &mut String, value: &impl Serialize) -> fmt::Result {
+    let cursor = Cursor::new(writer);
+    value
+        .serialize(&mut serde_json::Serializer::with_formatter(
+            &cursor,
+            JsonSubscriberFormatter,
+        ))
+        .map_err(|_| fmt::Error)
 }
 
 fn resolve_json_value<'a, S: Subscriber + for<'lookup> LookupSpan<'lookup>>(
@@ -298,6 +480,7 @@ fn resolve_json_value<'a, S: Subscriber + for<'lookup> LookupSpan<'lookup>>(
 ) -> Option<MaybeCached<'a, S>> {
     match value {
         JsonValue::Serde(value) => Some(MaybeCached::Serde(Cow::Borrowed(value))),
+        JsonValue::Cached(raw) => Some(MaybeCached::Cached(Cached::Raw(raw.clone()))),
         JsonValue::DynamicFromEvent(fun) => fun(event).map(Cow::Owned).map(MaybeCached::Serde),
         JsonValue::DynamicFromSpan(fun) => {
             event
@@ -318,8 +501,8 @@ fn resolve_json_value<'a, S: Subscriber + for<'lookup> LookupSpan<'lookup>>(
             event.parent_span().and_then(fun).map(MaybeCached::Cached)
         },
         JsonValue::DynamicRawFromEvent(fun) => Some(MaybeCached::Raw(fun)),
-        // This cannot be used with a static key so this should never be called
-        JsonValue::DynamicFromEventWithWriter(_) => None,
+        // The former is written directly by `format_event` and the latter is cached per callsite.
+        JsonValue::DynamicFromEventWithWriter(_) | JsonValue::StaticFromMetadata(_) => None,
     }
 }
 
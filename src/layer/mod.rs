@@ -1,22 +1,32 @@
 use std::{
     borrow::Cow,
     cell::RefCell,
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     io,
-    sync::{Arc, OnceLock},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        OnceLock,
+        PoisonError,
+        RwLock,
+    },
+    time::Instant,
 };
 
 use serde::Serialize;
 use tracing::{dispatcher::WeakDispatch, Dispatch};
 use tracing_core::{
+    callsite::Identifier,
+    field::{Field, Visit},
     span::{Attributes, Id, Record},
+    subscriber::Interest,
     Event,
+    Metadata,
     Subscriber,
 };
-use tracing_serde::fields::AsMap;
 use tracing_subscriber::{
-    fmt::{format::Writer, time::FormatTime, MakeWriter, TestWriter},
+    fmt::{format::Writer, time::FormatTime, writer::BoxMakeWriter, MakeWriter, TestWriter},
     layer::Context,
     registry::{LookupSpan, SpanRef},
     Layer,
@@ -24,6 +34,10 @@ use tracing_subscriber::{
 };
 
 mod event;
+#[cfg(feature = "__any-tracing-opentelemetry")]
+mod opentelemetry;
+mod otlp;
+mod presets;
 
 use event::EventRef;
 use uuid::Uuid;
@@ -31,8 +45,12 @@ use uuid::Uuid;
 use crate::{
     cached::Cached,
     field_writer::FieldWriter,
-    fields::{JsonFields, JsonFieldsInner},
-    serde::RenamedFields,
+    fields::{FieldRenames, JsonFields, JsonFieldsInner, SpanIds},
+    level_format::LevelFormat,
+    process::{self, CrateInfo},
+    span_format::SpanFormat,
+    span_selection::SpanSelection,
+    timestamp::TimestampFormat,
     visitor::JsonVisitor,
 };
 
@@ -46,11 +64,26 @@ pub struct JsonLayer<S: for<'lookup> LookupSpan<'lookup> = Registry, W = fn() ->
     log_internal_errors: bool,
     keyed_values: BTreeMap<SchemaKey, JsonValue<S>>,
     flattened_values: BTreeMap<FlatSchemaKey, JsonValue<S>>,
+    field_renames: Option<FieldRenames>,
+    span_format: SpanFormat,
+    /// Whether any value prints span fields serialized with this layer's span format and renames.
+    prints_span_fields: bool,
+    /// The start of an OTLP `resourceLogs` batch wrapping each line, up to the scope name.
+    resource_logs: Option<Box<str>>,
+    /// Additional named schemas written for each event to their own writers.
+    schemas: Vec<(String, JsonLayer<S, BoxMakeWriter>)>,
+    /// Whether any keyed value only depends on the callsite, computed when first needed after
+    /// the keyed values change.
+    has_static_values: OnceLock<bool>,
+    /// The serialized values which only depend on the callsite, keyed by the callsite. Each run of
+    /// adjacent keyed values is one string of `"key":value` entries.
+    callsite_values: RwLock<HashMap<Identifier, Box<[Box<str>]>>>,
     dispatch: OnceLock<WeakDispatch>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum SchemaKey {
+    /// The key escaped once when the schema is configured, so it can be written as is.
     Static(Cow<'static, str>),
 }
 
@@ -60,6 +93,10 @@ pub(crate) enum FlatSchemaKey {
     FlattenedEvent,
     FlattenedCurrentSpan,
     FlattenedSpanList,
+    #[cfg(feature = "__any-tracing-opentelemetry")]
+    DatadogTraceIds,
+    #[cfg(feature = "__any-tracing-opentelemetry")]
+    OpenTelemetryLogIds,
 }
 
 impl FlatSchemaKey {
@@ -70,29 +107,38 @@ impl FlatSchemaKey {
 
 impl From<Cow<'static, str>> for SchemaKey {
     fn from(value: Cow<'static, str>) -> Self {
-        Self::Static(value)
+        match value {
+            Cow::Borrowed(value) => Self::from(value),
+            Cow::Owned(value) => Self::from(value),
+        }
     }
 }
 
 impl From<&'static str> for SchemaKey {
     fn from(value: &'static str) -> Self {
-        Self::Static(value.into())
+        Self::Static(escape(value))
     }
 }
 
 impl From<String> for SchemaKey {
     fn from(value: String) -> Self {
-        Self::Static(value.into())
+        match escape(&value) {
+            Cow::Borrowed(_) => Self::Static(Cow::Owned(value)),
+            Cow::Owned(escaped) => Self::Static(Cow::Owned(escaped)),
+        }
     }
 }
 
 #[allow(clippy::type_complexity)]
 pub(crate) enum JsonValue<S: for<'lookup> LookupSpan<'lookup>> {
     Serde(serde_json::Value),
+    /// A value serialized once when the layer is configured.
+    Cached(Arc<str>),
     DynamicFromEvent(
         Box<dyn Fn(&EventRef<'_, '_, '_, S>) -> Option<serde_json::Value> + Send + Sync>,
     ),
     DynamicFromSpan(Box<dyn Fn(&SpanRef<'_, S>) -> Option<serde_json::Value> + Send + Sync>),
+    #[cfg_attr(not(feature = "__any-tracing-opentelemetry"), allow(dead_code))]
     DynamicFromSpanWithDispatch(
         Box<dyn Fn(&SpanRef<'_, S>, &Dispatch) -> Option<serde_json::Value> + Send + Sync>,
     ),
@@ -103,6 +149,11 @@ pub(crate) enum JsonValue<S: for<'lookup> LookupSpan<'lookup>> {
     DynamicFromEventWithWriter(
         Box<dyn Fn(&EventRef<'_, '_, '_, S>, &mut FieldWriter<'_>) + Send + Sync>,
     ),
+    /// A raw value which only depends on the callsite's metadata and is therefore serialized once
+    /// per callsite.
+    StaticFromMetadata(
+        Box<dyn Fn(&Metadata<'_>, &mut dyn fmt::Write) -> fmt::Result + Send + Sync>,
+    ),
 }
 
 impl<S, W> Layer<S> for JsonLayer<S, W>
@@ -114,6 +165,16 @@ where
         _ = self.dispatch.set(subscriber.downgrade());
     }
 
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if metadata.is_event() {
+            self.cache_callsite_values(metadata);
+            for (_, schema) in &self.schemas {
+                schema.cache_callsite_values(metadata);
+            }
+        }
+        Interest::always()
+    }
+
     fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
         let Some(span) = ctx.span(id) else {
             if self.log_internal_errors {
@@ -126,12 +187,17 @@ where
 
         if extensions.get_mut::<JsonFields>().is_none() {
             let mut fields = JsonFieldsInner::default();
-            let mut visitor = JsonVisitor::new(&mut fields);
+            let renames = self.field_renames.as_ref();
+            let mut visitor = JsonVisitor::new(&mut fields, renames);
             attrs.record(&mut visitor);
-            fields
-                .fields
-                .insert("name", serde_json::Value::from(attrs.metadata().name()));
-            let fields = fields.finish();
+            let ids = SpanIds {
+                id: id.into_u64(),
+                parent: span.parent().map(|parent| parent.id().into_u64()),
+                follows_from: Vec::new(),
+            };
+            let fields = self
+                .span_format
+                .finish(fields, attrs.metadata(), ids, renames);
             extensions.insert(fields);
         } else if self.log_internal_errors {
             eprintln!(
@@ -159,9 +225,32 @@ where
             return;
         };
 
-        values.record(&mut JsonVisitor::new(&mut fields.inner));
-        let serialized = serde_json::to_string(&fields).unwrap();
-        fields.serialized = Arc::from(serialized.as_str());
+        let renames = self.field_renames.as_ref();
+        values.record(&mut JsonVisitor::new(&mut fields.inner, renames));
+        self.span_format.update(fields, renames);
+    }
+
+    fn on_follows_from(&self, id: &Id, follows: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            if self.log_internal_errors {
+                eprintln!("[json-subscriber] Span not found, this is a bug.");
+            }
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        let Some(fields) = extensions.get_mut::<JsonFields>() else {
+            if self.log_internal_errors {
+                eprintln!(
+                    "[json-subscriber] Span was created but does not contain formatted fields, \
+                     this is a bug and some relations may have been lost."
+                );
+            }
+            return;
+        };
+
+        fields.ids.follows_from.push(follows.into_u64());
+        self.span_format.update(fields, self.field_renames.as_ref());
     }
 
     fn on_enter(&self, _id: &Id, _ctx: Context<'_, S>) {}
@@ -187,23 +276,19 @@ where
                 &mut b
             };
 
-            if self.format_event(&ctx, buf, event).is_ok() {
-                let mut writer = self.make_writer.make_writer_for(event.metadata());
-                let res = io::Write::write_all(&mut writer, buf.as_bytes());
-                if self.log_internal_errors {
-                    if let Err(e) = res {
-                        eprintln!(
-                            "[tracing-json] Unable to write an event to the Writer for this \
-                             Subscriber! Error: {e}\n",
-                        );
-                    }
-                }
-            } else if self.log_internal_errors {
-                eprintln!(
-                    "[tracing-json] Unable to format the following event. Name: {}; Fields: {:?}",
-                    event.metadata().name(),
-                    event.fields()
-                );
+            let mut event_ref = EventRef::new(
+                &ctx,
+                event,
+                self.field_renames.as_ref(),
+                !self.schemas.is_empty(),
+            );
+            let dispatch = self.dispatch.get();
+            self.write_event(&event_ref, dispatch, buf);
+
+            for (_, schema) in &self.schemas {
+                buf.clear();
+                event_ref.set_field_renames(schema.field_renames.as_ref());
+                schema.write_event(&event_ref, dispatch, buf);
             }
 
             buf.clear();
@@ -211,6 +296,48 @@ where
     }
 }
 
+impl<S, W> JsonLayer<S, W>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    W: for<'writer> MakeWriter<'writer> + 'static,
+{
+    /// Formats the event into `buf` according to this layer's schema and writes it to this layer's
+    /// writer.
+    fn write_event(
+        &self,
+        event: &EventRef<'_, '_, '_, S>,
+        dispatch: Option<&WeakDispatch>,
+        buf: &mut String,
+    ) {
+        if self.format_event(event, dispatch, buf).is_ok() {
+            // Events from `log` records carry their file and line in fields, so writers such as
+            // the journald one get them from the normalized metadata.
+            #[cfg(feature = "tracing-log")]
+            let normalized = event.normalized_metadata();
+            #[cfg(feature = "tracing-log")]
+            let metadata = normalized.as_ref().unwrap_or(event.metadata());
+            #[cfg(not(feature = "tracing-log"))]
+            let metadata = event.metadata();
+            let mut writer = self.make_writer.make_writer_for(metadata);
+            let res = io::Write::write_all(&mut writer, buf.as_bytes());
+            if self.log_internal_errors {
+                if let Err(e) = res {
+                    eprintln!(
+                        "[tracing-json] Unable to write an event to the Writer for this \
+                         Subscriber! Error: {e}\n",
+                    );
+                }
+            }
+        } else if self.log_internal_errors {
+            eprintln!(
+                "[tracing-json] Unable to format the following event. Name: {}; Fields: {:?}",
+                event.metadata().name(),
+                event.fields()
+            );
+        }
+    }
+}
+
 impl<S> JsonLayer<S>
 where
     S: Subscriber + for<'lookup> LookupSpan<'lookup>,
@@ -236,6 +363,13 @@ where
             log_internal_errors: false,
             keyed_values: BTreeMap::new(),
             flattened_values: BTreeMap::new(),
+            field_renames: None,
+            span_format: SpanFormat::default(),
+            prints_span_fields: false,
+            resource_logs: None,
+            schemas: Vec::new(),
+            has_static_values: OnceLock::new(),
+            callsite_values: RwLock::default(),
             dispatch: OnceLock::new(),
         }
     }
@@ -269,6 +403,13 @@ where
             log_internal_errors: self.log_internal_errors,
             keyed_values: self.keyed_values,
             flattened_values: self.flattened_values,
+            field_renames: self.field_renames,
+            span_format: self.span_format,
+            prints_span_fields: self.prints_span_fields,
+            resource_logs: self.resource_logs,
+            schemas: self.schemas,
+            has_static_values: self.has_static_values,
+            callsite_values: self.callsite_values,
             dispatch: self.dispatch,
         }
     }
@@ -333,6 +474,13 @@ where
             log_internal_errors: self.log_internal_errors,
             keyed_values: self.keyed_values,
             flattened_values: self.flattened_values,
+            field_renames: self.field_renames,
+            span_format: self.span_format,
+            prints_span_fields: self.prints_span_fields,
+            resource_logs: self.resource_logs,
+            schemas: self.schemas,
+            has_static_values: self.has_static_values,
+            callsite_values: self.callsite_values,
             dispatch: self.dispatch,
         }
     }
@@ -379,6 +527,13 @@ where
             log_internal_errors: self.log_internal_errors,
             keyed_values: self.keyed_values,
             flattened_values: self.flattened_values,
+            field_renames: self.field_renames,
+            span_format: self.span_format,
+            prints_span_fields: self.prints_span_fields,
+            resource_logs: self.resource_logs,
+            schemas: self.schemas,
+            has_static_values: self.has_static_values,
+            callsite_values: self.callsite_values,
             dispatch: self.dispatch,
         }
     }
@@ -400,7 +555,7 @@ where
     /// # fn get_hostname() -> &'static str { "localhost" }
     /// ```
     pub fn add_static_field(&mut self, key: impl Into<String>, value: serde_json::Value) {
-        self.keyed_values
+        self.keyed_values_mut()
             .insert(SchemaKey::from(key.into()), JsonValue::Serde(value));
     }
 
@@ -424,7 +579,18 @@ where
     /// # tracing_subscriber::registry().with(layer);
     /// ```
     pub fn remove_field(&mut self, key: impl Into<String>) {
-        self.keyed_values.remove(&SchemaKey::from(key.into()));
+        self.keyed_values_mut().remove(&SchemaKey::from(key.into()));
+    }
+
+    /// Mutably borrows the keyed values, forgetting the serialized callsite values and whether
+    /// there are any since they may no longer match.
+    fn keyed_values_mut(&mut self) -> &mut BTreeMap<SchemaKey, JsonValue<S>> {
+        self.has_static_values = OnceLock::new();
+        self.callsite_values
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        &mut self.keyed_values
     }
 
     pub(crate) fn remove_flattened_field(&mut self, key: &FlatSchemaKey) {
@@ -460,7 +626,7 @@ where
         for<'a> Fun: Fn(&'a Event<'_>, &Context<'_, S>) -> Option<Res> + Send + Sync + 'a,
         Res: serde::Serialize,
     {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicFromEvent(Box::new(move |event| {
                 serde_json::to_value(mapper(event.event(), event.context())?).ok()
@@ -536,7 +702,7 @@ where
         for<'a> Fun: Fn(&'a SpanRef<'_, S>) -> Option<Res> + Send + Sync + 'a,
         Res: serde::Serialize,
     {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicFromSpan(Box::new(move |span| {
                 serde_json::to_value(mapper(span)?).ok()
@@ -643,7 +809,7 @@ where
         for<'a> Fun: Fn(&'a Ext) -> Option<&'a Res> + Send + Sync + 'a,
         Res: serde::Serialize,
     {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicFromSpan(Box::new(move |span| {
                 let extensions = span.extensions();
@@ -706,7 +872,7 @@ where
         for<'a> Fun: Fn(&'a Ext) -> Option<Res> + Send + Sync + 'a,
         Res: serde::Serialize,
     {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicFromSpan(Box::new(move |span| {
                 let extensions = span.extensions();
@@ -717,11 +883,14 @@ where
     }
 
     /// Print all event fields in an object with the key as specified.
+    ///
+    /// The fields are printed in the order in which they are recorded, which is the order of their
+    /// declaration in the event with the message first.
     pub fn with_event(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
-            JsonValue::DynamicFromEvent(Box::new(move |event| {
-                serde_json::to_value(event.field_map()).ok()
+            JsonValue::DynamicFromEventWithWriter(Box::new(|event, writer| {
+                event.write_fields(event.field_renames(), writer);
             })),
         );
         self
@@ -735,12 +904,13 @@ where
     ///
     /// It's therefore preferable to use [`with_current_span`](Self::with_current_span) instead.
     pub fn with_top_level_flattened_current_span(&mut self) -> &mut Self {
+        self.prints_span_fields = true;
         self.flattened_values.insert(
             FlatSchemaKey::FlattenedCurrentSpan,
             JsonValue::DynamicCachedFromSpan(Box::new(move |span| {
                 span.extensions()
                     .get::<JsonFields>()
-                    .map(|fields| Cached::Raw(fields.serialized.clone()))
+                    .map(|fields| Cached::Raw(fields.serialized_fields.clone()))
             })),
         );
         self
@@ -756,34 +926,36 @@ where
     ///
     /// It's therefore preferable to use [`with_span_list`](Self::with_span_list) instead.
     pub fn with_top_level_flattened_span_list(&mut self) -> &mut Self {
+        self.with_top_level_flattened_selected_span_list(SpanSelection::default())
+    }
+
+    /// Print fields of selected parent spans, each as its own top level member of the JSON. See
+    /// [`SpanSelection`] for how spans can be selected.
+    ///
+    /// If multiple selected spans define the same field, the one furthest from the root span will
+    /// be kept.
+    ///
+    /// It is the user's responsibility to make sure that the field names will not clash with other
+    /// defined members of the output JSON. If they clash, invalid JSON with multiple fields with
+    /// the same key may be generated.
+    ///
+    /// It's therefore preferable to use [`with_selected_span_list`](Self::with_selected_span_list)
+    /// instead.
+    pub fn with_top_level_flattened_selected_span_list(
+        &mut self,
+        selection: SpanSelection,
+    ) -> &mut Self {
+        self.prints_span_fields = true;
         self.flattened_values.insert(
             FlatSchemaKey::FlattenedSpanList,
-            JsonValue::DynamicFromSpan(Box::new(|span| {
-                let fields =
-                    span.scope()
-                        .from_root()
-                        .fold(BTreeMap::new(), |mut accumulator, span| {
-                            let extensions = span.extensions();
-                            let Some(fields) = extensions.get::<JsonFields>() else {
-                                return accumulator;
-                            };
-                            accumulator.extend(
-                                fields
-                                    .inner
-                                    .fields
-                                    .iter()
-                                    .map(|(key, value)| (*key, value.clone())),
-                            );
-                            accumulator
-                        });
-
-                serde_json::to_value(fields).ok()
-            })),
+            JsonValue::DynamicFromSpan(Box::new(move |span| flatten_span_fields(span, &selection))),
         );
         self
     }
 
-    /// Print all event fields, each as its own top level member of the JSON.
+    /// Print all event fields, each as its own top level member of the JSON. Like in
+    /// [`with_event`](Self::with_event), the fields are printed in the order in which they are
+    /// recorded.
     ///
     /// It is the user's responsibility to make sure that the field names will not clash with other
     /// defined members of the output JSON. If they clash, invalid JSON with multiple fields with
@@ -793,8 +965,8 @@ where
     pub fn with_flattened_event(&mut self) -> &mut Self {
         self.flattened_values.insert(
             FlatSchemaKey::FlattenedEvent,
-            JsonValue::DynamicFromEvent(Box::new(move |event| {
-                serde_json::to_value(event.field_map()).ok()
+            JsonValue::DynamicFromEventWithWriter(Box::new(|event, writer| {
+                event.write_fields(event.field_renames(), writer);
             })),
         );
         self
@@ -827,31 +999,170 @@ where
     /// );
     /// # tracing_subscriber::registry().with(layer);
     ///
-    /// // This will produce something like `{"bar":3,"msg":"x",...}`
+    /// // This will produce something like `{"msg":"x","bar":3,...}`
     /// tracing::info!(foo = 3, "x");
     /// ```
     ///
     /// It is the user's responsibility to make sure that the field names will not clash with other
     /// defined members of the output JSON. If they clash, invalid JSON with multiple fields with
-    /// the same key may be generated.
+    /// the same key may be generated. If several fields of an event are renamed to the same key,
+    /// only the first one is printed.
     pub fn with_flattened_event_with_renames<F, T>(&mut self, renames: F, context: T) -> &mut Self
     where
         F: for<'a> Fn(&'a str, &'a T) -> &'a str + Send + Sync + 'static + Clone,
         T: Clone + Send + Sync + 'static,
     {
+        let renames = FieldRenames::new(renames, context);
         self.flattened_values.insert(
             FlatSchemaKey::FlattenedEvent,
-            JsonValue::DynamicFromEvent(Box::new(move |event| {
-                serde_json::to_value(RenamedFields::new(event.event(), renames.clone(), &context))
-                    .ok()
+            JsonValue::DynamicFromEventWithWriter(Box::new(move |event, writer| {
+                event.write_fields(Some(&renames), writer);
             })),
         );
         self
     }
 
+    /// Renames event and span fields wherever they are printed. This applies to
+    /// [`with_event`](Self::with_event), [`with_flattened_event`](Self::with_flattened_event),
+    /// [`with_current_span`](Self::with_current_span), [`with_span_list`](Self::with_span_list),
+    /// [`with_flattened_span_fields`](Self::with_flattened_span_fields) and the top level flattened
+    /// spans. The `name` key which holds the name of each span can be renamed too.
+    ///
+    /// The renames work the same way as in
+    /// [`with_flattened_event_with_renames`](Self::with_flattened_event_with_renames). The function
+    /// is called with the original field name and a user-defined context and it must produce the
+    /// new name.
+    ///
+    /// Span fields are renamed once when they are recorded and the renamed keys are cached, so
+    /// this adds no cost to emitting events in spans. This also means that the renames only take
+    /// effect for spans created after this method is called.
+    ///
+    /// If several fields of an event are renamed to the same key, only the first one is printed.
+    ///
+    /// ```rust
+    /// # use std::collections::HashMap;
+    /// # use tracing_subscriber::prelude::*;
+    ///
+    /// let mut layer = json_subscriber::JsonLayer::stdout();
+    /// layer.with_event("fields").with_current_span("span");
+    ///
+    /// let renames = HashMap::from([
+    ///     ("name".to_owned(), "span_name".to_owned()),
+    ///     ("message".to_owned(), "msg".to_owned()),
+    /// ]);
+    /// layer.with_field_renames(
+    ///     move |name, map| map.get(name).map_or(name, String::as_str),
+    ///     renames,
+    /// );
+    /// # tracing_subscriber::registry().with(layer);
+    ///
+    /// // This will produce something like
+    /// // `{"fields":{"msg":"x"},"span":{"span_name":"foo"},...}`
+    /// let _guard = tracing::info_span!("foo").entered();
+    /// tracing::info!("x");
+    /// ```
+    ///
+    /// It is the user's responsibility to make sure that no two fields are renamed to the same key.
+    pub fn with_field_renames<F, T>(&mut self, renames: F, context: T) -> &mut Self
+    where
+        F: for<'a> Fn(&'a str, &'a T) -> &'a str + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        self.field_renames = Some(FieldRenames::new(renames, context));
+        self
+    }
+
+    /// Sets how spans are represented by [`with_current_span`](Self::with_current_span) and
+    /// [`with_span_list`](Self::with_span_list). See [`SpanFormat`] for the available options.
+    ///
+    /// Spans are serialized once when they are created or recorded, so this only takes effect for
+    /// spans created after this method is called.
+    pub fn with_span_format(&mut self, span_format: SpanFormat) -> &mut Self {
+        self.span_format = span_format;
+        self
+    }
+
+    /// Adds another schema under `name`, which is written for every event to its own writer in
+    /// addition to this layer's output. Adding a schema with the same name replaces it.
+    ///
+    /// This is cheaper than stacking several [`JsonLayer`]s because the event's span is looked up
+    /// only once, span fields are serialized only once, by this layer, and shared by all schemas,
+    /// and event fields are visited only once for all schemas with the same field renames.
+    ///
+    /// # Panics
+    ///
+    /// Since spans are serialized by this layer, panics if `schema` has a
+    /// [span format](Self::with_span_format) other than the default or this layer's one, or if it
+    /// has [field renames](Self::with_field_renames) and prints span fields, for example with
+    /// [`with_current_span`](Self::with_current_span). Also panics if `schema` has schemas of its
+    /// own.
+    ///
+    /// # Examples
+    ///
+    /// Write human-oriented lines to stdout and a compact schema for a log shipper to stderr:
+    ///
+    /// ```rust
+    /// # use tracing_subscriber::prelude::*;
+    /// use json_subscriber::JsonLayer;
+    ///
+    /// let mut shipper = JsonLayer::stderr();
+    /// shipper.with_level("level").with_flattened_event();
+    ///
+    /// let mut layer = JsonLayer::stdout();
+    /// layer
+    ///     .with_timer("timestamp", tracing_subscriber::fmt::time::SystemTime)
+    ///     .with_level("level")
+    ///     .with_event("fields")
+    ///     .with_span_list("spans")
+    ///     .add_schema("shipper", shipper);
+    /// # tracing_subscriber::registry().with(layer);
+    /// ```
+    pub fn add_schema<W2>(&mut self, name: impl Into<String>, schema: JsonLayer<S, W2>) -> &mut Self
+    where
+        W2: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        assert!(
+            schema.span_format == SpanFormat::default() || schema.span_format == self.span_format,
+            "a schema cannot have its own span format because spans are serialized by the layer"
+        );
+        assert!(
+            schema.field_renames.is_none() || !schema.prints_span_fields,
+            "a schema printing span fields cannot have its own field renames because spans are \
+             serialized by the layer"
+        );
+        assert!(schema.schemas.is_empty(), "a schema cannot have schemas");
+
+        let name = name.into();
+        let schema = schema.map_writer(BoxMakeWriter::new);
+        if let Some(existing) = self.schema_mut(&name) {
+            *existing = schema;
+        } else {
+            self.schemas.push((name, schema));
+        }
+        self
+    }
+
+    /// Mutably borrows the schema added under `name` with [`add_schema`](Self::add_schema).
+    pub fn schema_mut(&mut self, name: &str) -> Option<&mut JsonLayer<S, BoxMakeWriter>> {
+        self.schemas
+            .iter_mut()
+            .find(|(schema_name, _)| schema_name == name)
+            .map(|(_, schema)| schema)
+    }
+
+    /// Removes the schema added under `name` with [`add_schema`](Self::add_schema) and returns it.
+    pub fn remove_schema(&mut self, name: &str) -> Option<JsonLayer<S, BoxMakeWriter>> {
+        let index = self
+            .schemas
+            .iter()
+            .position(|(schema_name, _)| schema_name == name)?;
+        Some(self.schemas.remove(index).1)
+    }
+
     /// Sets whether or not the log line will include the current span in formatted events.
     pub fn with_current_span(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.prints_span_fields = true;
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicCachedFromSpan(Box::new(move |span| {
                 span.extensions()
@@ -865,19 +1176,27 @@ where
     /// Sets whether or not the formatter will include a list (from root to leaf) of all currently
     /// entered spans in formatted events.
     pub fn with_span_list(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.with_selected_span_list(key, SpanSelection::default())
+    }
+
+    /// Sets the formatter to include a list (from root to leaf) of selected currently entered
+    /// spans in formatted events. See [`SpanSelection`] for how spans can be selected.
+    pub fn with_selected_span_list(
+        &mut self,
+        key: impl Into<String>,
+        selection: SpanSelection,
+    ) -> &mut Self {
+        self.prints_span_fields = true;
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
-            JsonValue::DynamicCachedFromSpan(Box::new(|span| {
-                Some(Cached::Array(
-                    span.scope()
-                        .from_root()
-                        .filter_map(|span| {
-                            span.extensions()
-                                .get::<JsonFields>()
-                                .map(|fields| fields.serialized.clone())
-                        })
-                        .collect::<Vec<_>>(),
-                ))
+            JsonValue::DynamicCachedFromSpan(Box::new(move |span| {
+                let mut serialized = Vec::new();
+                selection.for_each(span, |span| {
+                    if let Some(fields) = span.extensions().get::<JsonFields>() {
+                        serialized.push(fields.serialized.clone());
+                    }
+                });
+                Some(Cached::Array(serialized))
             })),
         );
         self
@@ -887,29 +1206,58 @@ where
     /// ancestor spans recorded the same field, the span closer to the leaf span overrides the
     /// values of spans that are closer to the root spans.
     pub fn with_flattened_span_fields(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.with_selected_flattened_span_fields(key, SpanSelection::default())
+    }
+
+    /// Sets the formatter to include an object containing fields of selected parent spans. See
+    /// [`SpanSelection`] for how spans can be selected. If multiple selected spans recorded the
+    /// same field, the span closer to the leaf span overrides the values of spans that are closer
+    /// to the root spans.
+    pub fn with_selected_flattened_span_fields(
+        &mut self,
+        key: impl Into<String>,
+        selection: SpanSelection,
+    ) -> &mut Self {
+        self.prints_span_fields = true;
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
-            JsonValue::DynamicFromSpan(Box::new(|span| {
-                let fields =
-                    span.scope()
-                        .from_root()
-                        .fold(BTreeMap::new(), |mut accumulator, span| {
-                            let extensions = span.extensions();
-                            let Some(fields) = extensions.get::<JsonFields>() else {
-                                return accumulator;
-                            };
-                            accumulator.extend(
-                                fields
-                                    .inner
-                                    .fields
-                                    .iter()
-                                    .map(|(key, value)| (*key, value.clone())),
-                            );
-                            accumulator
-                        });
+            JsonValue::DynamicFromSpan(Box::new(move |span| flatten_span_fields(span, &selection))),
+        );
+        self
+    }
 
-                serde_json::to_value(fields).ok()
-            })),
+    /// Sets whether or not the event's parent span is displayed together with how the parent was
+    /// determined. The value will be an object with the `source` key being one of:
+    ///
+    /// * `"explicit"` if the parent was set on the event with `parent: span`,
+    /// * `"contextual"` if the parent is the span the event was emitted in,
+    /// * `"explicitRoot"` if the event was made a root with `parent: None`, even if it was emitted
+    ///   in a span,
+    /// * `"root"` if the event was emitted outside of any span.
+    ///
+    /// Unless the event is a root, the object also contains the parent span's `name` and `id`.
+    ///
+    /// Use [`with_event_parent_scopes`](Self::with_event_parent_scopes) to also see which spans
+    /// were entered when an explicit parent was used.
+    pub fn with_event_parent(&mut self, key: impl Into<String>) -> &mut Self {
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::DynamicFromEvent(Box::new(|event| Some(event_parent(event, false)))),
+        );
+        self
+    }
+
+    /// Same as [`with_event_parent`](Self::with_event_parent) but when an event's explicit parent
+    /// differs from the span currently entered on the thread, the object also contains both span
+    /// chains. The `explicitScope` key holds the spans from the root to the explicit parent and the
+    /// `contextualScope` key holds the spans from the root to the currently entered span. Each span
+    /// is an object with its `name` and `id`.
+    ///
+    /// This can help with finding spans which are misused in asynchronous code.
+    pub fn with_event_parent_scopes(&mut self, key: impl Into<String>) -> &mut Self {
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::DynamicFromEvent(Box::new(|event| Some(event_parent(event, true)))),
         );
         self
     }
@@ -925,7 +1273,7 @@ where
         key: impl Into<String>,
         timer: T,
     ) -> &mut Self {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicFromEvent(Box::new(move |_| {
                 let mut timestamp = String::with_capacity(32);
@@ -936,12 +1284,31 @@ where
         self
     }
 
+    /// Print the time of the event under the given key using one of the built-in
+    /// [`TimestampFormat`]s.
+    ///
+    /// Unlike [`with_timer`](Self::with_timer), this can produce numbers and objects in addition to
+    /// strings and writes the timestamp directly to the output without allocating.
+    pub fn with_timestamp(&mut self, key: impl Into<String>, format: TimestampFormat) -> &mut Self {
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::DynamicRawFromEvent(Box::new(move |_event, writer| {
+                format.write_now(writer)
+            })),
+        );
+        self
+    }
+
     /// Sets whether or not an event's target is displayed. It will use the `target` key if so.
+    ///
+    /// With the `tracing-log` feature, this and the other metadata built-ins such as
+    /// [`with_file`](Self::with_file) use the metadata of the original `log` record for events
+    /// created from `log`.
     pub fn with_target(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
-            JsonValue::DynamicRawFromEvent(Box::new(|event, writer| {
-                write_escaped(writer, event.metadata().target())
+            JsonValue::StaticFromMetadata(Box::new(|metadata, writer| {
+                write_escaped(writer, metadata.target())
             })),
         );
 
@@ -953,10 +1320,10 @@ where
     ///
     /// [file]: tracing_core::Metadata::file
     pub fn with_file(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
-            JsonValue::DynamicRawFromEvent(Box::new(|event, writer| {
-                match event.metadata().file() {
+            JsonValue::StaticFromMetadata(Box::new(|metadata, writer| {
+                match metadata.file() {
                     Some(file) => write_escaped(writer, file),
                     None => write!(writer, "null"),
                 }
@@ -970,10 +1337,10 @@ where
     ///
     /// [line]: tracing_core::Metadata::line
     pub fn with_line_number(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
-            JsonValue::DynamicRawFromEvent(Box::new(|event, writer| {
-                match event.metadata().line() {
+            JsonValue::StaticFromMetadata(Box::new(|metadata, writer| {
+                match metadata.line() {
                     Some(line) => write!(writer, "{line}"),
                     None => write!(writer, "null"),
                 }
@@ -982,12 +1349,90 @@ where
         self
     }
 
+    /// Sets whether or not the module path of events created from `log` records is displayed. It
+    /// will use the `log.module_path` key if so.
+    ///
+    /// Nothing is displayed for events which do not come from `log`.
+    #[cfg(feature = "tracing-log")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tracing-log")))]
+    pub fn with_log_module_path(&mut self, key: impl Into<String>) -> &mut Self {
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::DynamicFromEvent(Box::new(|event| {
+                event
+                    .normalized_metadata()?
+                    .module_path()
+                    .map(serde_json::Value::from)
+            })),
+        );
+        self
+    }
+
+    /// Prints the event's file and line number as a single string such as `"src/main.rs:42"` which
+    /// can be recognized as a link by editors and terminals.
+    ///
+    /// If the line number is not known, only the file is printed. If the file is not known, the
+    /// value is `null`.
+    pub fn with_file_and_line(&mut self, key: impl Into<String>) -> &mut Self {
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::StaticFromMetadata(Box::new(|metadata, writer| {
+                let Some(file) = metadata.file() else {
+                    return write!(writer, "null");
+                };
+                writer.write_char('"')?;
+                write_escaped_contents(writer, file)?;
+                if let Some(line) = metadata.line() {
+                    write!(writer, ":{line}")?;
+                }
+                writer.write_char('"')
+            })),
+        );
+        self
+    }
+
+    /// Sets whether or not an event's [module path][module_path] is displayed.
+    ///
+    /// [module_path]: tracing_core::Metadata::module_path
+    pub fn with_module_path(&mut self, key: impl Into<String>) -> &mut Self {
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::StaticFromMetadata(Box::new(|metadata, writer| {
+                match metadata.module_path() {
+                    Some(module_path) => write_escaped(writer, module_path),
+                    None => write!(writer, "null"),
+                }
+            })),
+        );
+        self
+    }
+
+    /// Sets whether or not the [name] of the event's callsite is displayed. Unless set explicitly
+    /// in the macro, the name looks like `event src/main.rs:42`.
+    ///
+    /// [name]: tracing_core::Metadata::name
+    pub fn with_callsite_name(&mut self, key: impl Into<String>) -> &mut Self {
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::StaticFromMetadata(Box::new(|metadata, writer| {
+                write_escaped(writer, metadata.name())
+            })),
+        );
+        self
+    }
+
     /// Sets whether or not an event's level is displayed. It will use the `level` key if so.
     pub fn with_level(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.with_level_format(key, LevelFormat::Uppercase)
+    }
+
+    /// Prints the event's level using the given [`LevelFormat`], for example as a syslog severity
+    /// number instead of a name.
+    pub fn with_level_format(&mut self, key: impl Into<String>, format: LevelFormat) -> &mut Self {
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
-            JsonValue::DynamicRawFromEvent(Box::new(|event, writer| {
-                write_escaped(writer, event.metadata().level().as_str())
+            JsonValue::StaticFromMetadata(Box::new(move |metadata, writer| {
+                format.write(writer, *metadata.level())
             })),
         );
         self
@@ -998,7 +1443,7 @@ where
     ///
     /// [name]: std::thread#naming-threads
     pub fn with_thread_names(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicRawFromEvent(Box::new(|_event, writer| {
                 match std::thread::current().name() {
@@ -1015,7 +1460,7 @@ where
     ///
     /// [thread ID]: std::thread::ThreadId
     pub fn with_thread_ids(&mut self, key: impl Into<String>) -> &mut Self {
-        self.keyed_values.insert(
+        self.keyed_values_mut().insert(
             SchemaKey::from(key.into()),
             JsonValue::DynamicRawFromEvent(Box::new(|_event, writer| {
                 use std::fmt::Write;
@@ -1028,151 +1473,409 @@ where
         self
     }
 
-    /// Sets whether or not [OpenTelemetry] trace ID and span ID is displayed when formatting
-    /// events. It will use the `openTelemetry` key if so and the value will be an object with
-    /// `traceId` and `spanId` fields, each being a string.
+    /// Sets whether or not a sequence number is displayed when formatting events. It will use the
+    /// `sequence` key if so.
     ///
-    /// This works only if your `tracing-opentelemetry` version and this crate's features match. If
-    /// you update that dependency, you need to change the feature here or this call will do
-    /// nothing.
+    /// The number is shared by the whole process. It starts at zero and is incremented for every
+    /// line which displays it, from any layer or schema on any thread, so no two lines share a
+    /// number and it can be used to order lines whose timestamps are equal or go backwards.
+    pub fn with_sequence_number(&mut self, key: impl Into<String>) -> &mut Self {
+        static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::DynamicRawFromEvent(Box::new(|_event, writer| {
+                write!(writer, "{}", SEQUENCE.fetch_add(1, Ordering::Relaxed))
+            })),
+        );
+        self
+    }
+
+    /// Sets whether or not the time elapsed since this method was called is displayed when
+    /// formatting events. It will use the `uptime` key if so.
     ///
-    /// [OpenTelemetry]: https://opentelemetry.io
-    #[cfg(feature = "__any-tracing-opentelemetry")]
-    #[cfg_attr(docsrs, doc(feature = "__any-tracing-opentelemetry"))]
-    pub fn with_opentelemetry_ids(&mut self, display_opentelemetry_ids: bool) -> &mut Self {
-        if display_opentelemetry_ids {
-            self.keyed_values.insert(
-                SchemaKey::from("openTelemetry"),
-                JsonValue::DynamicFromSpanWithDispatch(Box::new(move |span, dispatch| {
-                    let mut ids: Option<serde_json::Value> = None;
-
-                    macro_rules! otel_extraction {
-                        ($feature:literal, $tracing_otel_crate:ident, $otel_crate:ident) => {
-                            #[cfg(feature = $feature)]
-                            {
-                                use $otel_crate::trace::{TraceContextExt, TraceId};
-                                ids = ids.or_else(|| {
-                                    span.extensions()
-                                        .get::<$tracing_otel_crate::OtelData>()
-                                        .and_then(|otel_data| {
-                                            // We should use the parent first if available because
-                                            // we can create a new trace and then change the
-                                            // parent. In that case the value in the builder is not
-                                            // updated.
-                                            let mut trace_id = otel_data
-                                                .parent_cx
-                                                .span()
-                                                .span_context()
-                                                .trace_id();
-                                            if trace_id == TraceId::INVALID {
-                                                trace_id = otel_data.builder.trace_id?;
-                                            }
-                                            let span_id = otel_data.builder.span_id?;
-                                            Some(serde_json::json!({
-                                                "traceId": trace_id.to_string(),
-                                                "spanId": span_id.to_string(),
-                                            }))
-                                        })
-                                });
-                            }
-                        };
-                    }
+    /// The value is a number of seconds with nanosecond precision measured by a monotonic clock, so
+    /// unlike the timestamp it never jumps when the system clock is adjusted.
+    pub fn with_uptime(&mut self, key: impl Into<String>) -> &mut Self {
+        let start = Instant::now();
+        self.keyed_values_mut().insert(
+            SchemaKey::from(key.into()),
+            JsonValue::DynamicRawFromEvent(Box::new(move |_event, writer| {
+                let elapsed = start.elapsed();
+                write!(
+                    writer,
+                    "{}.{:09}",
+                    elapsed.as_secs(),
+                    elapsed.subsec_nanos()
+                )
+            })),
+        );
+        self
+    }
 
-                    #[cfg(feature = "tracing-opentelemetry-0-33")]
-                    {
-                        ids = ids.or_else(|| {
-                            tracing_opentelemetry_0_33::get_otel_context(&span.id(), dispatch).map(
-                                |context| {
-                                    use opentelemetry_0_32::trace::TraceContextExt;
-
-                                    serde_json::json!({
-                                        "traceId": context.span().span_context().trace_id().to_string(),
-                                        "spanId": context.span().span_context().span_id().to_string(),
-                                    })
-                                },
-                            )
-                        });
-                    }
-                    #[cfg(feature = "tracing-opentelemetry-0-32")]
-                    {
-                        ids = ids.or_else(|| {
-                            span.extensions()
-                                .get::<tracing_opentelemetry_0_32::OtelData>()
-                                .and_then(|otel_data| {
-                                    let trace_id = otel_data.trace_id()?;
-                                    let span_id = otel_data.span_id()?;
-                                    Some(serde_json::json!({
-                                        "traceId": trace_id.to_string(),
-                                        "spanId": span_id.to_string(),
-                                    }))
-                                })
-                        });
-                    }
-                    otel_extraction!(
-                        "tracing-opentelemetry-0-31",
-                        tracing_opentelemetry_0_31,
-                        opentelemetry_0_30
-                    );
-                    otel_extraction!(
-                        "tracing-opentelemetry-0-30",
-                        tracing_opentelemetry_0_30,
-                        opentelemetry_0_29
-                    );
-                    otel_extraction!(
-                        "tracing-opentelemetry-0-29",
-                        tracing_opentelemetry_0_29,
-                        opentelemetry_0_28
-                    );
-                    otel_extraction!(
-                        "tracing-opentelemetry-0-28",
-                        tracing_opentelemetry_0_28,
-                        opentelemetry_0_27
-                    );
-                    otel_extraction!(
-                        "opentelemetry",
-                        tracing_opentelemetry_0_25,
-                        opentelemetry_0_24
-                    );
+    /// Sets whether or not the ID of the current process is displayed when formatting events. It
+    /// will use the `pid` key if so.
+    pub fn with_pid(&mut self, key: impl Into<String>) -> &mut Self {
+        self.add_cached_field(key.into(), &std::process::id());
+        self
+    }
 
-                    ids
-                })),
-            );
-        } else {
-            self.keyed_values.remove(&SchemaKey::from("openTelemetry"));
+    /// Sets whether or not the hostname of the machine is displayed when formatting events. It will
+    /// use the `hostname` key if so.
+    ///
+    /// The hostname is read only once when this method is called. Nothing is displayed if it is
+    /// not valid unicode.
+    ///
+    /// With the `hostname` feature, the hostname is asked from the operating system. Otherwise it
+    /// is taken from the `HOSTNAME` or `COMPUTERNAME` environment variable or from
+    /// `/etc/hostname`, and nothing is displayed if none of them is available.
+    pub fn with_hostname(&mut self, key: impl Into<String>) -> &mut Self {
+        if let Some(hostname) = process::hostname() {
+            self.add_cached_field(key.into(), &hostname);
         }
-
         self
     }
-}
 
-fn write_escaped(writer: &mut dyn fmt::Write, value: &str) -> Result<(), fmt::Error> {
-    let mut rest = value;
-    writer.write_str("\"")?;
-    let mut shift = 0;
-    while let Some(position) = rest
-        .get(shift..)
-        .and_then(|haystack| haystack.find(['\"', '\\']))
-    {
-        let (before, after) = rest.split_at(position + shift);
-        writer.write_str(before)?;
-        writer.write_char('\\')?;
-        rest = after;
-        shift = 1;
+    /// Sets whether or not the file name of the current executable is displayed when formatting
+    /// events. It will use the `executable` key if so.
+    ///
+    /// The name is read only once when this method is called. Nothing is displayed if it cannot be
+    /// determined.
+    pub fn with_executable_name(&mut self, key: impl Into<String>) -> &mut Self {
+        if let Some(name) = process::executable_name() {
+            self.add_cached_field(key.into(), &name);
+        }
+        self
     }
-    writer.write_str(rest)?;
-    writer.write_str("\"")
-}
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+    /// Prints the name and version of a crate under the given keys. Use the
+    /// [`crate_info!`](crate::crate_info) macro to get the info of your crate.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracing_subscriber::prelude::*;
+    /// let mut layer = json_subscriber::JsonLayer::stdout();
+    /// layer.with_crate_info("service.name", "service.version", json_subscriber::crate_info!());
+    /// # tracing_subscriber::registry().with(layer);
+    /// ```
+    pub fn with_crate_info(
+        &mut self,
+        name_key: impl Into<String>,
+        version_key: impl Into<String>,
+        info: CrateInfo,
+    ) -> &mut Self {
+        self.add_cached_field(name_key.into(), &info.name());
+        self.add_cached_field(version_key.into(), &info.version());
+        self
+    }
+
+    /// Prints the value of the environment variable `name` under the given key. This is useful for
+    /// identifiers that are passed to the process by its environment such as a container or pod
+    /// name.
+    ///
+    /// The variable is read only once when this method is called. Nothing is displayed if it is not
+    /// set or is not valid unicode.
+    pub fn with_env_var(&mut self, key: impl Into<String>, name: impl AsRef<str>) -> &mut Self {
+        if let Ok(value) = std::env::var(name.as_ref()) {
+            self.add_cached_field(key.into(), &value);
+        }
+        self
+    }
+
+    fn add_cached_field(&mut self, key: String, value: &impl Serialize) {
+        match serde_json::to_string(value) {
+            Ok(raw) => {
+                self.keyed_values_mut()
+                    .insert(SchemaKey::from(key), JsonValue::Cached(raw.into()));
+            },
+            Err(error) => {
+                if self.log_internal_errors {
+                    eprintln!("[json-subscriber] Unable to serialize value for key {key}: {error}");
+                }
+            },
+        }
+    }
+
+    /// Sets whether or not [OpenTelemetry] trace ID and span ID is displayed when formatting
+    /// events. It will use the `openTelemetry` key if so and the value will be an object with
+    /// `traceId` and `spanId` fields, each being a string.
+    ///
+    /// This works only if your `tracing-opentelemetry` version and this crate's features match. If
+    /// you update that dependency, you need to change the feature here or this call will do
+    /// nothing.
+    ///
+    /// [OpenTelemetry]: https://opentelemetry.io
+    #[cfg(feature = "__any-tracing-opentelemetry")]
+    #[cfg_attr(docsrs, doc(feature = "__any-tracing-opentelemetry"))]
+    pub fn with_opentelemetry_ids(&mut self, display_opentelemetry_ids: bool) -> &mut Self {
+        if display_opentelemetry_ids {
+            self.keyed_values_mut().insert(
+                SchemaKey::from("openTelemetry"),
+                JsonValue::DynamicFromSpanWithDispatch(Box::new(|span, dispatch| {
+                    let ids = opentelemetry::ids(span, dispatch)?;
+                    Some(serde_json::json!({
+                        "traceId": ids.trace_id_hex(),
+                        "spanId": ids.span_id_hex(),
+                    }))
+                })),
+            );
+        } else {
+            self.keyed_values_mut()
+                .remove(&SchemaKey::from("openTelemetry"));
+        }
+
+        self
+    }
+
+    /// Sets whether or not [OpenTelemetry] trace ID and span ID are displayed in the format used
+    /// by Datadog to correlate logs with traces. They will use the `dd.trace_id` and `dd.span_id`
+    /// keys if so, both as decimal numbers in strings. The trace ID only contains its lower 64
+    /// bits.
+    ///
+    /// The same caveats about matching versions as in
+    /// [`with_opentelemetry_ids`](Self::with_opentelemetry_ids) apply.
+    ///
+    /// [OpenTelemetry]: https://opentelemetry.io
+    #[cfg(feature = "__any-tracing-opentelemetry")]
+    #[cfg_attr(docsrs, doc(feature = "__any-tracing-opentelemetry"))]
+    pub fn with_datadog_trace_ids(&mut self, display_datadog_trace_ids: bool) -> &mut Self {
+        if display_datadog_trace_ids {
+            self.flattened_values.insert(
+                FlatSchemaKey::DatadogTraceIds,
+                JsonValue::DynamicFromSpanWithDispatch(Box::new(|span, dispatch| {
+                    let ids = opentelemetry::ids(span, dispatch)?;
+                    Some(serde_json::json!({
+                        "dd.trace_id": ids.trace_id_low_u64().to_string(),
+                        "dd.span_id": ids.span_id_u64().to_string(),
+                    }))
+                })),
+            );
+        } else {
+            self.flattened_values
+                .remove(&FlatSchemaKey::DatadogTraceIds);
+        }
+
+        self
+    }
+}
+
+fn flatten_span_fields<S>(
+    span: &SpanRef<'_, S>,
+    selection: &SpanSelection,
+) -> Option<serde_json::Value>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    let mut accumulator = BTreeMap::new();
+    selection.for_each(span, |span| {
+        let extensions = span.extensions();
+        let Some(fields) = extensions.get::<JsonFields>() else {
+            return;
+        };
+        accumulator.extend(
+            fields
+                .inner
+                .fields
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+    });
+
+    serde_json::to_value(accumulator).ok()
+}
+
+fn event_parent<S>(event: &EventRef<'_, '_, '_, S>, with_scopes: bool) -> serde_json::Value
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn scope<S>(span: Option<&SpanRef<'_, S>>) -> serde_json::Value
+    where
+        S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    {
+        span.into_iter()
+            .flat_map(|span| span.scope().from_root())
+            .map(|span| serde_json::json!({ "name": span.name(), "id": span.id().into_u64() }))
+            .collect()
+    }
+
+    let parent = event.parent_span();
+    let source = if event.event().is_root() {
+        "explicitRoot"
+    } else if parent.is_none() {
+        "root"
+    } else if event.event().is_contextual() {
+        "contextual"
+    } else {
+        "explicit"
+    };
+
+    let mut value = serde_json::json!({ "source": source });
+    if let Some(parent) = parent {
+        value["name"] = parent.name().into();
+        value["id"] = parent.id().into_u64().into();
+    }
+
+    if with_scopes && !event.event().is_contextual() {
+        let current = event.context().lookup_current();
+        let differs = match (parent, &current) {
+            (Some(parent), Some(current)) => parent.id() != current.id(),
+            (None, None) => false,
+            _ => true,
+        };
+        if differs {
+            value["explicitScope"] = scope(parent);
+            value["contextualScope"] = scope(current.as_ref());
+        }
+    }
+
+    value
+}
+
+/// Streams the event's fields directly into the output, without building a
+/// [`serde_json::Value`] first.
+fn write_event_fields(
+    event: &Event<'_>,
+    renames: Option<&FieldRenames>,
+    writer: &mut FieldWriter<'_>,
+) {
+    event.record(&mut EventFields {
+        renames,
+        renamed: Vec::new(),
+        writer,
+    });
+}
+
+/// A visitor writing each visited field of an event under its renamed key.
+struct EventFields<'a, 'w> {
+    renames: Option<&'a FieldRenames>,
+    /// The keys written so far if there are renames, which can give several fields the same key.
+    renamed: Vec<&'a str>,
+    writer: &'a mut FieldWriter<'w>,
+}
+
+impl EventFields<'_, '_> {
+    fn write(&mut self, field: &Field, value: impl Serialize) {
+        let name = field.name();
+        let name = if let Some(renames) = self.renames {
+            let name = renames.rename(name);
+            // Only the first field renamed to a key is written so that the output is valid JSON.
+            if self.renamed.contains(&name) {
+                return;
+            }
+            self.renamed.push(name);
+            name
+        } else {
+            name
+        };
+        // The field is skipped if its value cannot be serialized.
+        _ = self.writer.write_field(name, value);
+    }
+}
+
+impl Visit for EventFields<'_, '_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.write(field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.write(field, value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.write(field, value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.write(field, value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.write(field, value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.write(field, format_args!("{value:?}"));
+    }
+}
+
+pub(crate) fn write_escaped(writer: &mut dyn fmt::Write, value: &str) -> Result<(), fmt::Error> {
+    writer.write_str("\"")?;
+    write_escaped_contents(writer, value)?;
+    writer.write_str("\"")
+}
+
+/// Writes `"key":` for an already [escaped](escape) key, preceded by a comma if it is not the first
+/// entry of the object. All object keys are written by this function.
+pub(crate) fn write_key(writer: &mut String, escaped_key: &str, prefix_comma: bool) {
+    if prefix_comma {
+        writer.push(',');
+    }
+    writer.push('"');
+    writer.push_str(escaped_key);
+    writer.push_str("\":");
+}
+
+/// Returns the escaped contents of a JSON string, borrowing the value if nothing needs escaping.
+pub(crate) fn escape(value: &str) -> Cow<'_, str> {
+    if value
+        .bytes()
+        .any(|byte| matches!(byte, b'"' | b'\\' | 0x00..=0x1f))
+    {
+        let mut escaped = String::with_capacity(value.len() + 2);
+        // Writing to a `String` cannot fail.
+        _ = write_escaped_contents(&mut escaped, value);
+        Cow::Owned(escaped)
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Writes the escaped contents of a JSON string without the surrounding quotes.
+///
+/// Escapes quotes, backslashes and all control characters as required by RFC 8259. Runs of
+/// characters which do not need escaping, including all non-ASCII characters, are written at once.
+fn write_escaped_contents(writer: &mut dyn fmt::Write, value: &str) -> Result<(), fmt::Error> {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut start = 0;
+    // Bytes of multi-byte UTF-8 characters are never ASCII so splitting at escaped bytes always
+    // happens at character boundaries.
+    for (index, byte) in value.bytes().enumerate() {
+        let escaped = match byte {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0c => "\\f",
+            0x00..=0x1f => "",
+            _ => continue,
+        };
+        writer.write_str(&value[start..index])?;
+        if escaped.is_empty() {
+            writer.write_str("\\u00")?;
+            writer.write_char(char::from(HEX_DIGITS[usize::from(byte >> 4)]))?;
+            writer.write_char(char::from(HEX_DIGITS[usize::from(byte & 0xf)]))?;
+        } else {
+            writer.write_str(escaped)?;
+        }
+        start = index + 1;
+    }
+    writer.write_str(&value[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
     use serde_json::json;
     use tracing::subscriber::with_default;
     use tracing_subscriber::{registry, Layer, Registry};
 
     use super::JsonLayer;
-    use crate::tests::MockMakeWriter;
+    use crate::{tests::MockMakeWriter, Rfc3339, SpanFormat, SpanSelection, TimestampFormat};
 
     fn test_json<W, T>(
         expected: &serde_json::Value,
@@ -1256,4 +1959,847 @@ mod tests {
             );
         });
     }
+
+    #[test]
+    fn colliding_field_renames() {
+        fn rename<'a>(name: &'a str, (): &'a ()) -> &'a str {
+            if name == "user" {
+                "id"
+            } else {
+                name
+            }
+        }
+
+        let mut layer = JsonLayer::stdout();
+        layer.with_flattened_event_with_renames(rename, ());
+        let output = produce_log_line(layer, || tracing::info!(id = 1, user = 2, other = 3));
+        assert_eq!(output, "{\"id\":1,\"other\":3}\n");
+
+        let mut layer = JsonLayer::stdout();
+        layer.with_event("fields").with_field_renames(rename, ());
+        let output = produce_log_line(layer, || tracing::info!(user = 2, id = 1, other = 3));
+        assert_eq!(output, "{\"fields\":{\"id\":2,\"other\":3}}\n");
+    }
+
+    #[test]
+    fn field_renames() {
+        let renames = HashMap::from([
+            ("message".to_owned(), "msg".to_owned()),
+            ("name".to_owned(), "span_name".to_owned()),
+            ("answer".to_owned(), "response".to_owned()),
+            ("recorded".to_owned(), "late".to_owned()),
+        ]);
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_event("fields")
+            .with_current_span("span")
+            .with_span_list("spans")
+            .with_flattened_span_fields("flat")
+            .with_top_level_flattened_span_list()
+            .with_field_renames(
+                move |name, map| map.get(name).map_or(name, String::as_str),
+                renames,
+            );
+
+        let expected = json!({
+            "fields": {
+                "msg": "message",
+                "response": 1,
+            },
+            "span": {
+                "span_name": "inner",
+                "late": true,
+            },
+            "spans": [
+                {
+                    "span_name": "outer",
+                    "response": 42,
+                },
+                {
+                    "span_name": "inner",
+                    "late": true,
+                },
+            ],
+            "flat": {
+                "span_name": "inner",
+                "response": 42,
+                "late": true,
+            },
+            "span_name": "inner",
+            "response": 42,
+            "late": true,
+        });
+
+        test_json(&expected, layer, || {
+            let _outer = tracing::info_span!("outer", answer = 42).entered();
+            let inner = tracing::info_span!("inner", recorded = tracing::field::Empty).entered();
+            inner.record("recorded", true);
+            tracing::info!(answer = 1, "message");
+        });
+    }
+
+    fn nested_spans_with_event() {
+        let _a = tracing::info_span!("a", a = 1).entered();
+        let _b = tracing::debug_span!(target: "other", "b", b = 2).entered();
+        let _c = tracing::info_span!("c", c = 3).entered();
+        let _d = tracing::trace_span!("d", d = 4).entered();
+        tracing::info!("message");
+    }
+
+    #[test]
+    fn selected_span_list() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_selected_span_list("innermost", SpanSelection::new().innermost(2))
+            .with_selected_span_list("outermost", SpanSelection::new().outermost(2))
+            .with_selected_span_list(
+                "filtered",
+                SpanSelection::new()
+                    .with_target_prefix("json_subscriber::layer")
+                    .with_max_level(tracing::Level::DEBUG)
+                    .innermost(5),
+            )
+            .with_selected_span_list("names", SpanSelection::new().with_name("b").with_name("d"))
+            .with_selected_span_list("none", SpanSelection::new().innermost(0));
+
+        let expected = json!({
+            "innermost": [{"name": "c", "c": 3}, {"name": "d", "d": 4}],
+            "outermost": [{"name": "a", "a": 1}, {"name": "b", "b": 2}],
+            "filtered": [{"name": "a", "a": 1}, {"name": "c", "c": 3}],
+            "names": [{"name": "b", "b": 2}, {"name": "d", "d": 4}],
+            "none": [],
+        });
+
+        test_json(&expected, layer, nested_spans_with_event);
+    }
+
+    #[test]
+    fn selected_flattened_span_fields() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_selected_flattened_span_fields(
+                "flat",
+                SpanSelection::new().with_max_level(tracing::Level::INFO),
+            )
+            .with_top_level_flattened_selected_span_list(SpanSelection::new().innermost(1));
+
+        let expected = json!({
+            "flat": {"name": "c", "a": 1, "c": 3},
+            "name": "d",
+            "d": 4,
+        });
+
+        test_json(&expected, layer, nested_spans_with_event);
+    }
+
+    #[test]
+    fn span_ids() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_event("fields")
+            .with_span_list("spans")
+            .with_span_format(
+                SpanFormat::nested()
+                    .with_id(true)
+                    .with_parent_id(true)
+                    .with_follows_from(true),
+            );
+
+        let line = produce_log_line(layer, || {
+            let cause = tracing::info_span!("cause");
+            let parent = tracing::info_span!("parent").entered();
+            let child = tracing::info_span!("child");
+            child.follows_from(&cause);
+            let _child = child.entered();
+            tracing::info!(cause = cause.id().unwrap().into_u64(), "message");
+            drop(parent);
+        });
+        let line = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+        let spans = line["spans"].as_array().unwrap();
+
+        assert_eq!(spans[0]["name"], "parent");
+        assert_eq!(spans[0]["parentId"], serde_json::Value::Null);
+        assert_eq!(spans[0]["followsFrom"], json!([]));
+        assert_eq!(spans[1]["name"], "child");
+        assert_eq!(spans[1]["parentId"], spans[0]["id"]);
+        assert_eq!(spans[1]["followsFrom"], json!([line["fields"]["cause"]]));
+        assert_ne!(spans[0]["id"], spans[1]["id"]);
+    }
+
+    #[test]
+    fn span_format_renames_only_name() {
+        for span_format in [SpanFormat::flat(), SpanFormat::nested()] {
+            let mut layer = JsonLayer::stdout();
+            layer
+                .with_current_span("span")
+                .with_span_format(span_format.with_target(true))
+                .with_field_renames(
+                    |name, ()| {
+                        match name {
+                            "name" => "span_name",
+                            "target" => "renamed_target",
+                            "answer" => "response",
+                            name => name,
+                        }
+                    },
+                    (),
+                );
+
+            let output = produce_log_line(layer, || {
+                let _span = tracing::info_span!("span", answer = 42).entered();
+                tracing::info!("event");
+            });
+            let line = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+            let span = &line["span"];
+
+            assert_eq!(span["span_name"], "span");
+            assert_eq!(span["target"], "json_subscriber::layer::tests");
+            assert!(span.get("name").is_none());
+            assert!(span.get("renamed_target").is_none());
+            let fields = span.get("fields").unwrap_or(span);
+            assert_eq!(fields["response"], 42);
+        }
+    }
+
+    #[test]
+    fn flat_span_metadata_not_flattened() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_level("level")
+            .with_target("target")
+            .with_current_span("span")
+            .with_top_level_flattened_span_list()
+            .with_span_format(
+                SpanFormat::flat()
+                    .with_target(true)
+                    .with_level(true)
+                    .with_id(true)
+                    .with_parent_id(true)
+                    .with_follows_from(true),
+            );
+
+        let output = produce_log_line(layer, || {
+            let _span = tracing::warn_span!("span", answer = 42, id = "user id").entered();
+            tracing::info!("event");
+        });
+
+        // The metadata keys only appear for the event and in the current span, they were not
+        // flattened into the line.
+        let count = |key: &str| output.matches(&format!("\"{key}\":")).count();
+        assert_eq!(count("level"), 2);
+        assert_eq!(count("target"), 2);
+        assert_eq!(count("parentId"), 1);
+        assert_eq!(count("followsFrom"), 1);
+        let line = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["target"], "json_subscriber::layer::tests");
+        assert_eq!(line["answer"], 42);
+        assert_eq!(line["id"], "user id");
+        assert_eq!(line["name"], "span");
+        assert!(line.get("parentId").is_none());
+        assert!(line.get("followsFrom").is_none());
+        assert_eq!(line["span"]["level"], "WARN");
+        assert_eq!(line["span"]["target"], "json_subscriber::layer::tests");
+        assert_eq!(line["span"]["parentId"], serde_json::Value::Null);
+        assert!(line["span"]["id"].is_u64());
+    }
+
+    #[test]
+    fn event_parent() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_event_parent("parent")
+            .with_event_parent_scopes("scopes");
+
+        let output = produce_log_line(layer, || {
+            tracing::info!("outside");
+            let other = tracing::info_span!("other");
+            let _current = tracing::info_span!("current").entered();
+            tracing::info!("contextual");
+            tracing::info!(parent: &other, "explicit");
+            tracing::info!(parent: None, "root");
+        });
+        let lines = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        let id = |value: &serde_json::Value| value["id"].as_u64().unwrap();
+
+        assert_eq!(
+            lines[0],
+            json!({"parent": {"source": "root"}, "scopes": {"source": "root"}})
+        );
+
+        assert_eq!(lines[1]["parent"]["source"], "contextual");
+        assert_eq!(lines[1]["parent"]["name"], "current");
+        assert_eq!(lines[1]["parent"], lines[1]["scopes"]);
+        let current_id = id(&lines[1]["parent"]);
+
+        assert_eq!(lines[2]["parent"]["source"], "explicit");
+        assert_eq!(lines[2]["parent"]["name"], "other");
+        let other_id = id(&lines[2]["parent"]);
+        assert_eq!(
+            lines[2]["scopes"],
+            json!({
+                "source": "explicit",
+                "name": "other",
+                "id": other_id,
+                "explicitScope": [{"name": "other", "id": other_id}],
+                "contextualScope": [{"name": "current", "id": current_id}],
+            }),
+        );
+
+        assert_eq!(lines[3]["parent"], json!({"source": "explicitRoot"}));
+        assert_eq!(
+            lines[3]["scopes"],
+            json!({
+                "source": "explicitRoot",
+                "explicitScope": [],
+                "contextualScope": [{"name": "current", "id": current_id}],
+            }),
+        );
+    }
+
+    #[test]
+    fn timestamp_formats() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_timestamp("millis", TimestampFormat::UnixMillis)
+            .with_timestamp("gcp", TimestampFormat::SecondsAndNanos)
+            .with_timestamp(
+                "rfc3339",
+                TimestampFormat::Rfc3339(Rfc3339::new().with_precision(0)),
+            );
+
+        let before = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        let output = produce_log_line(layer, || tracing::info!("event"));
+        let line = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+
+        let millis = line["millis"].as_u64().unwrap();
+        assert!(u128::from(millis) >= before.as_millis());
+        assert!(line["gcp"]["seconds"].as_u64().unwrap() >= before.as_secs());
+        assert!(line["gcp"]["nanos"].as_u64().unwrap() < 1_000_000_000);
+        let rfc3339 = line["rfc3339"].as_str().unwrap();
+        assert_eq!(rfc3339.len(), "2024-06-10T06:13:20Z".len());
+        assert!(rfc3339.ends_with('Z'));
+    }
+
+    #[test]
+    fn sequence_number_and_uptime() {
+        let schema_writer = MockMakeWriter::default();
+        let mut schema = JsonLayer::new(schema_writer.clone());
+        schema.with_sequence_number("seq");
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_sequence_number("seq")
+            .with_uptime("uptime")
+            .add_schema("schema", schema);
+
+        let output = produce_log_line(layer, || {
+            tracing::info!("first");
+            tracing::info!("second");
+            tracing::info!("third");
+        });
+        let lines = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        // Other tests may print sequence numbers concurrently, so only the order is known.
+        let sequence = lines
+            .iter()
+            .map(|line| line["seq"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert!(sequence.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // The layer and its schema share the sequence.
+        let schema_buf = schema_writer.buf();
+        let schema_sequence = std::str::from_utf8(&schema_buf)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["seq"].as_u64())
+            .collect::<Option<Vec<_>>>()
+            .unwrap();
+        assert_eq!(schema_sequence.len(), 3);
+        assert!(schema_sequence
+            .iter()
+            .all(|number| !sequence.contains(number)));
+
+        let uptimes = lines
+            .iter()
+            .map(|line| line["uptime"].as_f64().unwrap())
+            .collect::<Vec<_>>();
+        assert!(uptimes.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn process_metadata() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_pid("pid")
+            .with_hostname("hostname")
+            .with_executable_name("executable")
+            .with_crate_info("service", "version", crate::crate_info!())
+            // Cargo sets this variable for the test binary, so the environment does not need to
+            // be modified.
+            .with_env_var("pod", "CARGO_PKG_NAME")
+            .with_env_var("missing", "JSON_SUBSCRIBER_TEST_MISSING");
+
+        let output = produce_log_line(layer, || tracing::info!("event"));
+        let line = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+
+        assert_eq!(line["pid"], std::process::id());
+        // The hostname may not be found without the `hostname` feature.
+        assert_eq!(
+            line.get("hostname").and_then(serde_json::Value::as_str),
+            crate::process::hostname().as_deref(),
+        );
+        assert!(line["executable"]
+            .as_str()
+            .unwrap()
+            .starts_with("json_subscriber"));
+        assert_eq!(line["service"], "json-subscriber");
+        assert_eq!(line["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(line["pod"], "json-subscriber");
+        assert!(line.get("missing").is_none());
+    }
+
+    #[cfg(feature = "tracing-log")]
+    #[test]
+    fn log_metadata() {
+        use tracing_log::log;
+
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_target("target")
+            .with_file("file")
+            .with_line_number("line")
+            .with_level("level")
+            .with_log_module_path("log.module_path");
+
+        let output = produce_log_line(layer, || {
+            let record = log::Record::builder()
+                .args(format_args!("from log"))
+                .level(log::Level::Warn)
+                .target("my_crate::module")
+                .module_path_static(Some("my_crate::module"))
+                .file_static(Some("src/module.rs"))
+                .line(Some(42))
+                .build();
+            tracing_log::format_trace(&record).unwrap();
+            tracing::info!("from tracing");
+        });
+        let lines = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[0],
+            json!({
+                "target": "my_crate::module",
+                "file": "src/module.rs",
+                "line": 42,
+                "level": "WARN",
+                "log.module_path": "my_crate::module",
+            })
+        );
+        assert_eq!(lines[1]["target"], "json_subscriber::layer::tests");
+        assert!(lines[1].get("log.module_path").is_none());
+    }
+
+    #[test]
+    fn callsite_metadata() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_module_path("module")
+            .with_callsite_name("callsite")
+            .with_file_and_line("location");
+
+        let output = produce_log_line(layer, || {
+            tracing::info!(name: "named", "event");
+        });
+        let line = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+
+        assert_eq!(line["module"], "json_subscriber::layer::tests");
+        assert_eq!(line["callsite"], "named");
+        let location = line["location"].as_str().unwrap();
+        let (file, line_number) = location.rsplit_once(':').unwrap();
+        assert_eq!(file, file!());
+        assert!(line_number.parse::<u32>().is_ok());
+    }
+
+    #[test]
+    fn callsite_values_keep_key_order() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_target("a_target")
+            .with_line_number("c_line")
+            .with_level("e_level");
+        layer.add_static_field("b_static", json!(1));
+        layer.add_static_field("d_static", json!("two"));
+
+        let line = line!() + 1;
+        let output = produce_log_line(layer, || tracing::info!(target: "callsite", "event"));
+
+        assert_eq!(
+            output,
+            format!(
+                "{{\"a_target\":\"callsite\",\"b_static\":1,\"c_line\":{line},\"d_static\":\"two\"\
+                 ,\"e_level\":\"INFO\"}}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn callsite_values_merge_adjacent_keys() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_level("a_level")
+            .with_target("b_target")
+            .with_line_number("d_line")
+            .with_file("e_file");
+        layer.add_static_field("c_static", json!(null));
+
+        let line = line!() + 1;
+        let output = produce_log_line(layer, || tracing::info!(target: "callsite", "event"));
+
+        assert_eq!(
+            output,
+            format!(
+                "{{\"a_level\":\"INFO\",\"b_target\":\"callsite\",\"c_static\":null,\"d_line\":\
+                 {line},\"e_file\":\"{}\"}}\n",
+                file!(),
+            )
+        );
+    }
+
+    #[test]
+    fn multiple_schemas() {
+        let verbose = MockMakeWriter::default();
+        let compact = MockMakeWriter::default();
+
+        let mut compact_schema = JsonLayer::new(compact.clone());
+        compact_schema
+            .with_level("lvl")
+            .with_flattened_event()
+            .with_field_renames(|name, ()| if name == "message" { "msg" } else { name }, ());
+        let mut layer = JsonLayer::new(verbose.clone());
+        layer
+            .with_event("fields")
+            .with_current_span("span")
+            .add_schema("compact", compact_schema)
+            .add_schema("removed", JsonLayer::stdout());
+        assert!(layer.remove_schema("removed").is_some());
+        assert!(layer.schema_mut("removed").is_none());
+        layer
+            .schema_mut("compact")
+            .unwrap()
+            .with_current_span("span");
+
+        with_default(layer.with_subscriber(registry()), || {
+            let _span = tracing::info_span!("request", id = 7).entered();
+            tracing::warn!(answer = 42, "hello");
+        });
+
+        let verbose = serde_json::from_slice::<serde_json::Value>(&verbose.buf()).unwrap();
+        let compact = serde_json::from_slice::<serde_json::Value>(&compact.buf()).unwrap();
+        assert_eq!(
+            verbose,
+            json!({
+                "fields": { "message": "hello", "answer": 42 },
+                "span": { "name": "request", "id": 7 },
+            })
+        );
+        assert_eq!(
+            compact,
+            json!({
+                "lvl": "WARN",
+                "msg": "hello",
+                "answer": 42,
+                "span": { "name": "request", "id": 7 },
+            })
+        );
+    }
+
+    #[test]
+    fn schemas_visit_event_fields_once() {
+        use std::{
+            fmt,
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        static VISITS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted;
+
+        impl fmt::Debug for Counted {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                VISITS.fetch_add(1, Ordering::Relaxed);
+                f.write_str("counted")
+            }
+        }
+
+        let main = MockMakeWriter::default();
+        let same = MockMakeWriter::default();
+        let renamed = MockMakeWriter::default();
+
+        let mut same_schema = JsonLayer::new(same.clone());
+        same_schema.with_flattened_event();
+        let mut renamed_schema = JsonLayer::new(renamed.clone());
+        renamed_schema
+            .with_flattened_event()
+            .with_field_renames(|name, ()| if name == "value" { "v" } else { name }, ());
+        let mut layer = JsonLayer::new(main.clone());
+        layer
+            .with_event("fields")
+            .add_schema("same", same_schema)
+            .add_schema("renamed", renamed_schema);
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::info!(value = ?Counted);
+        });
+
+        // Once for the layer and the schema without renames, once for the renamed schema.
+        assert_eq!(VISITS.load(Ordering::Relaxed), 2);
+        let main = serde_json::from_slice::<serde_json::Value>(&main.buf()).unwrap();
+        let same = serde_json::from_slice::<serde_json::Value>(&same.buf()).unwrap();
+        let renamed = serde_json::from_slice::<serde_json::Value>(&renamed.buf()).unwrap();
+        assert_eq!(main, json!({ "fields": { "value": "counted" } }));
+        assert_eq!(same, json!({ "value": "counted" }));
+        assert_eq!(renamed, json!({ "v": "counted" }));
+    }
+
+    #[test]
+    #[should_panic = "a schema cannot have its own span format"]
+    fn schema_span_format_rejected() {
+        let mut schema = JsonLayer::<Registry>::stdout();
+        schema
+            .with_current_span("span")
+            .with_span_format(SpanFormat::nested());
+        JsonLayer::stdout().add_schema("schema", schema);
+    }
+
+    #[test]
+    #[should_panic = "a schema printing span fields cannot have its own field renames"]
+    fn schema_span_renames_rejected() {
+        let mut schema = JsonLayer::<Registry>::stdout();
+        schema.with_current_span("span").with_field_renames(
+            |name, ()| if name == "name" { "span_name" } else { name },
+            (),
+        );
+        JsonLayer::stdout().add_schema("schema", schema);
+    }
+
+    #[test]
+    fn callsite_values_follow_schema_changes() {
+        let make_writer = MockMakeWriter::default();
+        let mut layer = JsonLayer::new(make_writer.clone());
+        layer.with_level("level").with_target("target");
+        let (layer, handle) = tracing_subscriber::reload::Layer::new(layer);
+
+        with_default(layer.with_subscriber(registry()), || {
+            for index in 0..3 {
+                if index == 2 {
+                    handle
+                        .modify(|layer| {
+                            layer.remove_field("target");
+                            layer.with_line_number("line");
+                        })
+                        .unwrap();
+                }
+                tracing::info!(target: "callsite", "event");
+            }
+        });
+
+        let buf = make_writer.buf();
+        let lines = std::str::from_utf8(&buf)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines[0], json!({ "level": "INFO", "target": "callsite" }));
+        assert_eq!(lines[1], lines[0]);
+        assert_eq!(lines[2]["level"], "INFO");
+        assert!(lines[2].get("target").is_none());
+        assert!(lines[2]["line"].is_u64());
+    }
+
+    #[test]
+    fn escaping() {
+        let mut output = String::new();
+        super::write_escaped(
+            &mut output,
+            "a\"b\\c\nd\re\tf\u{8}g\u{c}h\u{0}i\u{1f}j\u{7f}kž🦀",
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            r#""a\"b\\c\nd\re\tf\bg\fh\u0000i\u001fj"#.to_owned() + "\u{7f}kž🦀\""
+        );
+    }
+
+    /// A callsite created at runtime so that its target, file and field name can be arbitrary.
+    struct LeakedCallsite(std::sync::OnceLock<&'static tracing::Metadata<'static>>);
+
+    impl tracing::callsite::Callsite for LeakedCallsite {
+        fn set_interest(&self, _interest: tracing_core::Interest) {}
+
+        fn metadata(&self) -> &tracing::Metadata<'_> {
+            self.0.get().unwrap()
+        }
+    }
+
+    fn leak(value: &str) -> &'static str {
+        Box::leak(value.into())
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn arbitrary_strings_produce_valid_json(
+            target: String,
+            file: String,
+            thread_name in "[^\u{0}]*",
+            field_name: String,
+            value: String,
+            key: String,
+        ) {
+            let static_key = format!("static {key}");
+            let level_key = format!("level {key}");
+            let callsite: &'static LeakedCallsite =
+                Box::leak(Box::new(LeakedCallsite(std::sync::OnceLock::new())));
+            let field_names: &'static [&'static str] = Box::leak(Box::new([leak(&field_name)]));
+            let metadata: &'static tracing::Metadata<'static> =
+                Box::leak(Box::new(tracing::Metadata::new(
+                    leak(&target),
+                    leak(&target),
+                    tracing::Level::INFO,
+                    Some(leak(&file)),
+                    Some(42),
+                    Some(leak(&target)),
+                    tracing::field::FieldSet::new(
+                        field_names,
+                        tracing_core::identify_callsite!(callsite),
+                    ),
+                    tracing_core::Kind::EVENT,
+                )));
+            callsite.0.set(metadata).unwrap();
+
+            let make_writer = MockMakeWriter::default();
+            let mut layer = JsonLayer::new(make_writer.clone());
+            layer
+                .with_target("target")
+                .with_file("file")
+                .with_file_and_line("location")
+                .with_module_path("module")
+                .with_callsite_name("name")
+                .with_thread_names("thread")
+                .with_event("fields")
+                .with_level(level_key.clone());
+            layer.add_static_field(static_key.clone(), json!(value));
+            let subscriber = layer.with_subscriber(registry());
+
+            std::thread::scope(|scope| {
+                std::thread::Builder::new()
+                    .name(thread_name.clone())
+                    .spawn_scoped(scope, || {
+                        with_default(subscriber, || {
+                            let field = metadata.fields().field(field_names[0]).unwrap();
+                            let values = [(&field, Some(&value.as_str() as &dyn tracing::Value))];
+                            tracing::Event::dispatch(
+                                metadata,
+                                &metadata.fields().value_set(&values),
+                            );
+                        });
+                    })
+                    .unwrap()
+                    .join()
+                    .unwrap();
+            });
+
+            let buf = make_writer.buf();
+            let line = serde_json::from_slice::<serde_json::Value>(&buf).unwrap();
+            proptest::prop_assert_eq!(&line["target"], &json!(target));
+            proptest::prop_assert_eq!(&line["file"], &json!(file));
+            proptest::prop_assert_eq!(&line["location"], &json!(format!("{file}:42")));
+            proptest::prop_assert_eq!(&line["module"], &json!(target));
+            proptest::prop_assert_eq!(&line["name"], &json!(target));
+            proptest::prop_assert_eq!(&line["thread"], &json!(thread_name));
+            proptest::prop_assert_eq!(&line["fields"][&field_name], &json!(value));
+            proptest::prop_assert_eq!(&line[&level_key], &json!("INFO"));
+            proptest::prop_assert_eq!(&line[&static_key], &json!(value));
+        }
+    }
+
+    #[test]
+    fn escaped_keys() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_level("le\"vel")
+            .with_current_span("sp\\an")
+            .with_event("fi\nelds")
+            .add_static_field("sta\"tic", json!({ "ne\"sted": true }));
+        layer.add_static_field("removed\"", json!(1));
+        layer.remove_field("removed\"");
+        layer.add_multiple_dynamic_fields(|_, _, writer| {
+            writer.write_field("dyn\"amic", 1).unwrap();
+        });
+        layer.with_flattened_event();
+
+        let expected = json!({
+            "le\"vel": "INFO",
+            "sp\\an": { "name": "span", "ke\"y": 1 },
+            "fi\nelds": { "message": "event", "fla\\t": 2 },
+            "message": "event",
+            "fla\\t": 2,
+            "sta\"tic": { "ne\"sted": true },
+            "dyn\"amic": 1,
+        });
+        test_json(&expected, layer, || {
+            let _span = tracing::info_span!("span", "ke\"y" = 1).entered();
+            tracing::info!("fla\\t" = 2, "event");
+        });
+    }
+
+    #[test]
+    fn streamed_event_fields() {
+        let mut layer = JsonLayer::stdout();
+        layer
+            .with_event("fields")
+            .with_flattened_event_with_renames(
+                |name, ()| if name == "ok" { "renamed" } else { name },
+                (),
+            );
+
+        let fields = json!({
+            "message": "hello",
+            "big": u64::MAX,
+            "negative": -3,
+            "ratio": 0.5,
+            "nan": null,
+            "ok": true,
+            "text": "a\"b",
+            "list": "[1, 2]",
+        });
+        let mut flattened = fields.clone();
+        let flattened_map = flattened.as_object_mut().unwrap();
+        let ok = flattened_map.remove("ok").unwrap();
+        flattened_map.insert("renamed".to_owned(), ok);
+        flattened_map.insert("fields".to_owned(), fields);
+
+        test_json(&flattened, layer, || {
+            tracing::info!(
+                big = u64::MAX,
+                negative = -3,
+                ratio = 0.5,
+                nan = f64::NAN,
+                ok = true,
+                text = "a\"b",
+                list = ?[1, 2],
+                "hello"
+            );
+        });
+    }
 }
@@ -0,0 +1,145 @@
+use tracing::Dispatch;
+use tracing_core::Subscriber;
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+/// Trace and span IDs assigned to a span by `tracing-opentelemetry`.
+pub(super) struct OpenTelemetryIds {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+}
+
+impl OpenTelemetryIds {
+    pub(super) fn trace_id_hex(&self) -> String {
+        format!("{:032x}", u128::from_be_bytes(self.trace_id))
+    }
+
+    pub(super) fn span_id_hex(&self) -> String {
+        format!("{:016x}", u64::from_be_bytes(self.span_id))
+    }
+
+    /// The lower 64 bits of the trace ID which is how Datadog correlates OpenTelemetry traces.
+    pub(super) fn trace_id_low_u64(&self) -> u64 {
+        let mut low = [0; 8];
+        low.copy_from_slice(&self.trace_id[8..]);
+        u64::from_be_bytes(low)
+    }
+
+    pub(super) fn span_id_u64(&self) -> u64 {
+        u64::from_be_bytes(self.span_id)
+    }
+}
+
+/// Extracts the OpenTelemetry IDs of `span` using whichever `tracing-opentelemetry` versions are
+/// enabled.
+#[cfg_attr(
+    not(feature = "tracing-opentelemetry-0-33"),
+    allow(unused_variables, unused_mut)
+)]
+pub(super) fn ids<S>(span: &SpanRef<'_, S>, dispatch: &Dispatch) -> Option<OpenTelemetryIds>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    let mut ids: Option<OpenTelemetryIds> = None;
+
+    macro_rules! otel_extraction {
+        ($feature:literal, $tracing_otel_crate:ident, $otel_crate:ident) => {
+            #[cfg(feature = $feature)]
+            {
+                use $otel_crate::trace::{TraceContextExt, TraceId};
+                ids = ids.or_else(|| {
+                    span.extensions()
+                        .get::<$tracing_otel_crate::OtelData>()
+                        .and_then(|otel_data| {
+                            // We should use the parent first if available because we can create a
+                            // new trace and then change the parent. In that case the value in the
+                            // builder is not updated.
+                            let mut trace_id = otel_data.parent_cx.span().span_context().trace_id();
+                            if trace_id == TraceId::INVALID {
+                                trace_id = otel_data.builder.trace_id?;
+                            }
+                            let span_id = otel_data.builder.span_id?;
+                            Some(OpenTelemetryIds {
+                                trace_id: trace_id.to_bytes(),
+                                span_id: span_id.to_bytes(),
+                            })
+                        })
+                });
+            }
+        };
+    }
+
+    #[cfg(feature = "tracing-opentelemetry-0-33")]
+    {
+        ids = ids.or_else(|| {
+            tracing_opentelemetry_0_33::get_otel_context(&span.id(), dispatch).map(|context| {
+                use opentelemetry_0_32::trace::TraceContextExt;
+
+                let span = context.span();
+                let span_context = span.span_context();
+                OpenTelemetryIds {
+                    trace_id: span_context.trace_id().to_bytes(),
+                    span_id: span_context.span_id().to_bytes(),
+                }
+            })
+        });
+    }
+    #[cfg(feature = "tracing-opentelemetry-0-32")]
+    {
+        ids = ids.or_else(|| {
+            span.extensions()
+                .get::<tracing_opentelemetry_0_32::OtelData>()
+                .and_then(|otel_data| {
+                    Some(OpenTelemetryIds {
+                        trace_id: otel_data.trace_id()?.to_bytes(),
+                        span_id: otel_data.span_id()?.to_bytes(),
+                    })
+                })
+        });
+    }
+    otel_extraction!(
+        "tracing-opentelemetry-0-31",
+        tracing_opentelemetry_0_31,
+        opentelemetry_0_30
+    );
+    otel_extraction!(
+        "tracing-opentelemetry-0-30",
+        tracing_opentelemetry_0_30,
+        opentelemetry_0_29
+    );
+    otel_extraction!(
+        "tracing-opentelemetry-0-29",
+        tracing_opentelemetry_0_29,
+        opentelemetry_0_28
+    );
+    otel_extraction!(
+        "tracing-opentelemetry-0-28",
+        tracing_opentelemetry_0_28,
+        opentelemetry_0_27
+    );
+    otel_extraction!(
+        "opentelemetry",
+        tracing_opentelemetry_0_25,
+        opentelemetry_0_24
+    );
+
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpenTelemetryIds;
+
+    #[test]
+    fn id_encodings() {
+        let ids = OpenTelemetryIds {
+            trace_id: [
+                0xfb, 0x4b, 0x6a, 0xe1, 0xfa, 0x52, 0xd4, 0xaa, 0, 0, 0, 0, 0, 0, 0x01, 0x02,
+            ],
+            span_id: [0x35, 0x24, 0x9d, 0x86, 0xbf, 0xbc, 0xf7, 0x74],
+        };
+        assert_eq!(ids.trace_id_hex(), "fb4b6ae1fa52d4aa0000000000000102");
+        assert_eq!(ids.span_id_hex(), "35249d86bfbcf774");
+        assert_eq!(ids.trace_id_low_u64(), 0x0102);
+        assert_eq!(ids.span_id_u64(), 0x3524_9d86_bfbc_f774);
+    }
+}
@@ -0,0 +1,437 @@
+use std::fmt;
+
+use serde::{
+    ser::{SerializeMap, SerializeSeq},
+    Serialize,
+    Serializer,
+};
+use tracing_core::{field::Visit, Event, Field, Subscriber};
+#[cfg(feature = "tracing-log")]
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::{fmt::MakeWriter, registry::LookupSpan};
+
+use super::{write_escaped, FlatSchemaKey, JsonLayer, JsonValue, SchemaKey};
+use crate::{fields::FieldRenames, LevelFormat, TimestampFormat};
+
+const RESOURCE_LOGS_SUFFIX: &str = "]}]}]}";
+
+impl<S> JsonLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    /// Creates a [`JsonLayer`] producing each event as an [OpenTelemetry `LogRecord`] in the
+    /// [OTLP/JSON] encoding.
+    ///
+    /// Each line contains `timeUnixNano`, `severityNumber`, `severityText`, the event's message
+    /// as `body` and all other event fields as `attributes`. With one of the OpenTelemetry
+    /// features, `traceId` and `spanId` are added when the event is inside a span with an
+    /// OpenTelemetry context.
+    ///
+    /// Use [`with_resource_logs`](Self::with_resource_logs) to wrap each record into a
+    /// `resourceLogs` batch which can be read by the OpenTelemetry collector's file receiver.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracing_subscriber::prelude::*;
+    /// let mut layer = json_subscriber::JsonLayer::opentelemetry_logs(std::io::stdout);
+    /// layer.with_resource_logs([("service.name", "my-service".into())]);
+    /// # tracing_subscriber::registry().with(layer);
+    /// ```
+    ///
+    /// [OpenTelemetry `LogRecord`]: https://opentelemetry.io/docs/specs/otel/logs/data-model/
+    /// [OTLP/JSON]: https://opentelemetry.io/docs/specs/otlp/#json-protobuf-encoding
+    pub fn opentelemetry_logs<W>(make_writer: W) -> JsonLayer<S, W>
+    where
+        W: for<'writer> MakeWriter<'writer> + 'static,
+    {
+        let mut layer = JsonLayer::new(make_writer);
+        // 64-bit integers are strings in the JSON encoding of protobuf.
+        layer.keyed_values_mut().insert(
+            SchemaKey::from("timeUnixNano"),
+            JsonValue::DynamicRawFromEvent(Box::new(|_event, writer| {
+                writer.write_char('"')?;
+                TimestampFormat::UnixNanos.write_now(writer)?;
+                writer.write_char('"')
+            })),
+        );
+        layer
+            .with_level_format("severityNumber", LevelFormat::OpenTelemetry)
+            .with_level("severityText");
+        layer.flattened_values.insert(
+            FlatSchemaKey::FlattenedEvent,
+            JsonValue::DynamicFromEventWithWriter(Box::new(|event, writer| {
+                let mut visitor = LogRecordFields {
+                    renames: event.field_renames(),
+                    body: None,
+                    attributes: Vec::new(),
+                };
+                event.event().record(&mut visitor);
+                if let Some(body) = visitor.body {
+                    _ = writer.write_field("body", body);
+                }
+                _ = writer.write_field("attributes", KeyValues(&visitor.attributes));
+            })),
+        );
+        #[cfg(feature = "__any-tracing-opentelemetry")]
+        layer.flattened_values.insert(
+            FlatSchemaKey::OpenTelemetryLogIds,
+            JsonValue::DynamicFromSpanWithDispatch(Box::new(|span, dispatch| {
+                let ids = super::opentelemetry::ids(span, dispatch)?;
+                Some(serde_json::json!({
+                    "traceId": ids.trace_id_hex(),
+                    "spanId": ids.span_id_hex(),
+                }))
+            })),
+        );
+        layer
+    }
+}
+
+impl<S, W> JsonLayer<S, W>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    /// Wraps each line into an OTLP/JSON `resourceLogs` batch with a single record. The resource
+    /// has the given attributes and the instrumentation scope is named after the event's target.
+    ///
+    /// This is meant to be used with [`opentelemetry_logs`](JsonLayer::opentelemetry_logs) so
+    /// that the output can be tailed by the OpenTelemetry collector's `otlpjsonfile` receiver.
+    /// Attributes with `null` values are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracing_subscriber::prelude::*;
+    /// let mut layer = json_subscriber::JsonLayer::opentelemetry_logs(std::io::stdout);
+    /// layer.with_resource_logs([
+    ///     ("service.name", "my-service".into()),
+    ///     ("service.instance.id", std::process::id().into()),
+    /// ]);
+    /// # tracing_subscriber::registry().with(layer);
+    /// ```
+    pub fn with_resource_logs(
+        &mut self,
+        resource_attributes: impl IntoIterator<Item = (impl Into<String>, serde_json::Value)>,
+    ) -> &mut Self {
+        let attributes = resource_attributes
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.into(), AnyValue::from(&value)))
+            .collect::<Vec<_>>();
+
+        let mut prefix = String::from("{\"resourceLogs\":[{\"resource\":{\"attributes\":");
+        match serde_json::to_string(&KeyValues(&attributes)) {
+            Ok(attributes) => prefix.push_str(&attributes),
+            Err(error) => {
+                if self.log_internal_errors {
+                    eprintln!("[json-subscriber] Unable to serialize resource attributes: {error}");
+                }
+                prefix.push_str("[]");
+            },
+        }
+        prefix.push_str("},\"scopeLogs\":[{\"scope\":{\"name\":");
+        self.resource_logs = Some(prefix.into());
+        self
+    }
+
+    /// Writes the start of a `resourceLogs` batch if [`with_resource_logs`] was called.
+    ///
+    /// [`with_resource_logs`]: Self::with_resource_logs
+    pub(super) fn write_resource_logs_prefix(
+        &self,
+        writer: &mut String,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let Some(prefix) = &self.resource_logs else {
+            return Ok(());
+        };
+        writer.push_str(prefix);
+
+        #[cfg(feature = "tracing-log")]
+        let normalized = event.normalized_metadata();
+        #[cfg(feature = "tracing-log")]
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        #[cfg(not(feature = "tracing-log"))]
+        let metadata = event.metadata();
+
+        write_escaped(writer, metadata.target())?;
+        writer.push_str("},\"logRecords\":[");
+        Ok(())
+    }
+
+    /// Closes the `resourceLogs` batch opened by [`write_resource_logs_prefix`].
+    ///
+    /// [`write_resource_logs_prefix`]: Self::write_resource_logs_prefix
+    pub(super) fn write_resource_logs_suffix(&self, writer: &mut String) {
+        if self.resource_logs.is_some() {
+            writer.push_str(RESOURCE_LOGS_SUFFIX);
+        }
+    }
+}
+
+/// An OTLP `AnyValue`, serialized as an object with a single key naming its type.
+#[derive(Debug, Clone, PartialEq)]
+enum AnyValue {
+    String(String),
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    Array(Vec<AnyValue>),
+    KvList(Vec<(String, AnyValue)>),
+}
+
+impl Serialize for AnyValue {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        struct Values<'a, T>(&'a T);
+
+        impl<T: Serialize> Serialize for Values<'_, T> {
+            fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("values", self.0)?;
+                map.end()
+            }
+        }
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        match self {
+            Self::String(value) => map.serialize_entry("stringValue", value)?,
+            Self::Bool(value) => map.serialize_entry("boolValue", value)?,
+            Self::Int(value) => map.serialize_entry("intValue", &value.to_string())?,
+            Self::Double(value) if value.is_nan() => map.serialize_entry("doubleValue", "NaN")?,
+            Self::Double(value) if value.is_infinite() => {
+                let value = if value.is_sign_positive() {
+                    "Infinity"
+                } else {
+                    "-Infinity"
+                };
+                map.serialize_entry("doubleValue", value)?;
+            },
+            Self::Double(value) => map.serialize_entry("doubleValue", value)?,
+            Self::Array(values) => map.serialize_entry("arrayValue", &Values(values))?,
+            Self::KvList(values) => {
+                map.serialize_entry("kvlistValue", &Values(&KeyValues(values)))?;
+            },
+        }
+        map.end()
+    }
+}
+
+impl From<&serde_json::Value> for AnyValue {
+    fn from(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Self::String(String::new()),
+            serde_json::Value::Bool(value) => Self::Bool(*value),
+            serde_json::Value::Number(number) => {
+                if let Some(number) = number.as_i64() {
+                    Self::Int(number)
+                } else if let Some(number) = number.as_f64().filter(|_| number.is_f64()) {
+                    Self::Double(number)
+                } else {
+                    Self::String(number.to_string())
+                }
+            },
+            serde_json::Value::String(value) => Self::String(value.clone()),
+            serde_json::Value::Array(values) => {
+                Self::Array(values.iter().map(Self::from).collect())
+            },
+            serde_json::Value::Object(map) => {
+                Self::KvList(
+                    map.iter()
+                        .filter(|(_, value)| !value.is_null())
+                        .map(|(key, value)| (key.clone(), Self::from(value)))
+                        .collect(),
+                )
+            },
+        }
+    }
+}
+
+/// A list of OTLP `KeyValue`s, serialized as `[{"key":..,"value":..}]`.
+struct KeyValues<'a, K>(&'a [(K, AnyValue)]);
+
+impl<K: AsRef<str>> Serialize for KeyValues<'_, K> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        struct KeyValue<'a>(&'a str, &'a AnyValue);
+
+        impl Serialize for KeyValue<'_> {
+            fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("key", self.0)?;
+                map.serialize_entry("value", self.1)?;
+                map.end()
+            }
+        }
+
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for (key, value) in self.0 {
+            seq.serialize_element(&KeyValue(key.as_ref(), value))?;
+        }
+        seq.end()
+    }
+}
+
+/// Collects event fields into the body and attributes of a `LogRecord`.
+struct LogRecordFields<'a> {
+    renames: Option<&'a FieldRenames>,
+    body: Option<AnyValue>,
+    attributes: Vec<(&'a str, AnyValue)>,
+}
+
+impl LogRecordFields<'_> {
+    fn record(&mut self, field: &Field, value: AnyValue) {
+        if field.name() == "message" {
+            self.body = Some(value);
+            return;
+        }
+
+        let name = field.name();
+        let name = name.strip_prefix("r#").unwrap_or(name);
+        let name = self.renames.map_or(name, |renames| renames.rename(name));
+        self.attributes.push((name, value));
+    }
+}
+
+impl Visit for LogRecordFields<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, AnyValue::Double(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, AnyValue::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        let value = i64::try_from(value)
+            .map_or_else(|_| AnyValue::String(value.to_string()), AnyValue::Int);
+        self.record(field, value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, AnyValue::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, AnyValue::String(value.to_owned()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, AnyValue::String(format!("{value:?}")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::{registry, Layer, Registry};
+
+    use super::AnyValue;
+    use crate::{tests::MockMakeWriter, JsonLayer};
+
+    fn produce(layer: JsonLayer<Registry, MockMakeWriter>, producer: impl FnOnce()) {
+        with_default(layer.with_subscriber(registry()), producer);
+    }
+
+    #[test]
+    fn log_record() {
+        let make_writer = MockMakeWriter::default();
+        let layer = JsonLayer::opentelemetry_logs(make_writer.clone());
+        produce(layer, || {
+            tracing::warn!(
+                answer = 42,
+                big = u64::MAX,
+                ratio = 0.5,
+                ok = true,
+                user = "alice",
+                "hello otlp"
+            );
+        });
+
+        let line = serde_json::from_slice::<Value>(&make_writer.buf()).unwrap();
+        let line = line.as_object().unwrap();
+
+        let time = line["timeUnixNano"].as_str().unwrap();
+        assert!(time.parse::<u128>().unwrap() > 1_700_000_000_000_000_000);
+        assert_eq!(line["severityNumber"], 13);
+        assert_eq!(line["severityText"], "WARN");
+        assert_eq!(line["body"], json!({ "stringValue": "hello otlp" }));
+        assert_eq!(
+            line["attributes"],
+            json!([
+                { "key": "answer", "value": { "intValue": "42" } },
+                { "key": "big", "value": { "stringValue": "18446744073709551615" } },
+                { "key": "ratio", "value": { "doubleValue": 0.5 } },
+                { "key": "ok", "value": { "boolValue": true } },
+                { "key": "user", "value": { "stringValue": "alice" } },
+            ])
+        );
+        assert_eq!(line.len(), 5);
+    }
+
+    #[test]
+    fn resource_logs() {
+        let make_writer = MockMakeWriter::default();
+        let mut layer = JsonLayer::opentelemetry_logs(make_writer.clone());
+        layer.with_resource_logs([
+            ("service.name", json!("test-service")),
+            ("skipped", Value::Null),
+            ("tags", json!(["a", 1])),
+            ("nested", json!({ "x": 1.5 })),
+        ]);
+        produce(layer, || {
+            tracing::info!(target: "my\"target", "wrapped");
+        });
+
+        let buf = make_writer.buf();
+        let text = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        let line = serde_json::from_str::<Value>(text).unwrap();
+
+        let resource_logs = line["resourceLogs"].as_array().unwrap();
+        assert_eq!(resource_logs.len(), 1);
+        assert_eq!(
+            resource_logs[0]["resource"],
+            json!({
+                "attributes": [
+                    { "key": "service.name", "value": { "stringValue": "test-service" } },
+                    {
+                        "key": "tags",
+                        "value": {
+                            "arrayValue": {
+                                "values": [{ "stringValue": "a" }, { "intValue": "1" }],
+                            },
+                        },
+                    },
+                    {
+                        "key": "nested",
+                        "value": {
+                            "kvlistValue": {
+                                "values": [{ "key": "x", "value": { "doubleValue": 1.5 } }],
+                            },
+                        },
+                    },
+                ],
+            })
+        );
+        let scope_logs = &resource_logs[0]["scopeLogs"][0];
+        assert_eq!(scope_logs["scope"]["name"], "my\"target");
+        let record = &scope_logs["logRecords"][0];
+        assert_eq!(record["severityNumber"], 9);
+        assert_eq!(record["body"]["stringValue"], "wrapped");
+    }
+
+    #[test]
+    fn non_finite_doubles() {
+        let values = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY]
+            .map(|value| serde_json::to_value(AnyValue::Double(value)).unwrap());
+        assert_eq!(
+            values,
+            [
+                json!({ "doubleValue": "NaN" }),
+                json!({ "doubleValue": "Infinity" }),
+                json!({ "doubleValue": "-Infinity" }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use std::fmt;
+
+use serde::Serialize;
+use tracing_core::{field::Visit, Field, Subscriber};
+use tracing_subscriber::{fmt::MakeWriter, registry::LookupSpan};
+
+use super::{FlatSchemaKey, JsonLayer, JsonValue};
+use crate::{fields::FieldRenames, process, FieldWriter, LevelFormat, TimestampFormat};
+
+impl<S> JsonLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    /// Creates a [`JsonLayer`] producing lines in the format of [Bunyan] which can also be read by
+    /// pino tools such as `pino-pretty`.
+    ///
+    /// Each line contains `"v":0`, the numeric `level`, the given `name`, `hostname`, `pid`, `time`
+    /// as milliseconds since the Unix epoch and the event's message as `msg`. Other event fields
+    /// are added to the top level object. Since all of these are required by Bunyan, `hostname` is
+    /// `"unknown"` if it cannot be found and `msg` is empty for events without a message.
+    ///
+    /// The returned layer can be further customized, for example to add the span list.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracing_subscriber::prelude::*;
+    /// let layer = json_subscriber::JsonLayer::bunyan(std::io::stdout, "my-service");
+    /// # tracing_subscriber::registry().with(layer);
+    /// ```
+    ///
+    /// [Bunyan]: https://github.com/trentm/node-bunyan#core-fields
+    pub fn bunyan<W>(make_writer: W, name: impl Into<String>) -> JsonLayer<S, W>
+    where
+        W: for<'writer> MakeWriter<'writer> + 'static,
+    {
+        let mut layer = JsonLayer::new(make_writer);
+        layer.add_cached_field("v".to_owned(), &0);
+        layer.add_cached_field("name".to_owned(), &name.into());
+        layer.add_cached_field("hostname".to_owned(), &process::hostname_or_unknown());
+        layer
+            .with_level_format("level", LevelFormat::Bunyan)
+            .with_pid("pid")
+            .with_timestamp("time", TimestampFormat::UnixMillis)
+            .with_field_renames(|name, ()| if name == "message" { "msg" } else { name }, ());
+        layer.flattened_values.insert(
+            FlatSchemaKey::FlattenedEvent,
+            JsonValue::DynamicFromEventWithWriter(Box::new(|event, writer| {
+                let renames = event.field_renames();
+                event.write_fields(renames, writer);
+                if event.metadata().fields().field("message").is_none() {
+                    let key = renames.map_or("message", |renames| renames.rename("message"));
+                    _ = writer.write_field(key, "");
+                }
+            })),
+        );
+        layer
+    }
+
+    /// Creates a [`JsonLayer`] producing [GELF 1.1] messages for Graylog. Use it with
+    /// [`GelfUdpWriter`](crate::writer::GelfUdpWriter) to send the messages over UDP.
+    ///
+    /// Each message contains `version`, `host`, `timestamp` as fractional seconds, the syslog
+    /// severity as `level` and the first line of the event's message as `short_message`. If the
+    /// message has multiple lines, it is also included whole as `full_message`. Events without a
+    /// message use the callsite name as `short_message`. Since GELF requires `host`, it is
+    /// `"unknown"` if the hostname cannot be found.
+    ///
+    /// All other event fields are added as additional fields, prefixed with `_`. Since GELF
+    /// reserves `_id`, a field named `id` is sent as `__id`. Characters other than ASCII letters,
+    /// digits, `_`, `.` and `-` are not allowed in GELF field names and are replaced with `_`.
+    /// Booleans and values recorded with [`Debug`](std::fmt::Debug) are sent as strings because
+    /// GELF only allows strings and numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracing_subscriber::prelude::*;
+    /// # fn main() -> std::io::Result<()> {
+    /// use json_subscriber::{writer::GelfUdpWriter, JsonLayer};
+    ///
+    /// let layer = JsonLayer::gelf(GelfUdpWriter::new("127.0.0.1:12201")?);
+    /// # tracing_subscriber::registry().with(layer);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [GELF 1.1]: https://go2docs.graylog.org/current/getting_in_log_data/gelf.html
+    pub fn gelf<W>(make_writer: W) -> JsonLayer<S, W>
+    where
+        W: for<'writer> MakeWriter<'writer> + 'static,
+    {
+        let mut layer = JsonLayer::new(make_writer);
+        layer.add_cached_field("version".to_owned(), &"1.1");
+        layer.add_cached_field("host".to_owned(), &process::hostname_or_unknown());
+        layer
+            .with_timestamp("timestamp", TimestampFormat::UnixSecondsFractional)
+            .with_level_format("level", LevelFormat::Syslog);
+        layer.flattened_values.insert(
+            FlatSchemaKey::FlattenedEvent,
+            JsonValue::DynamicFromEventWithWriter(Box::new(|event, writer| {
+                let mut visitor = GelfFields {
+                    writer,
+                    renames: event.field_renames(),
+                    key: String::new(),
+                    message: None,
+                };
+                event.event().record(&mut visitor);
+                let GelfFields {
+                    writer, message, ..
+                } = visitor;
+
+                let message = message.as_deref().unwrap_or(event.metadata().name());
+                match message.split_once('\n') {
+                    Some((first_line, _)) => {
+                        _ = writer.write_field("short_message", first_line);
+                        _ = writer.write_field("full_message", message);
+                    },
+                    None => {
+                        _ = writer.write_field("short_message", message);
+                    },
+                }
+            })),
+        );
+        layer
+    }
+
+    /// Creates a [`JsonLayer`] producing logs in the format expected by [Datadog].
+    ///
+    /// Each line contains the `timestamp`, the level as `status`, the target as `logger.name` and
+    /// all event fields including `message` at the top level. The service, environment and
+    /// version are taken from the `DD_SERVICE`, `DD_ENV` and `DD_VERSION` environment variables
+    /// used by Datadog's unified service tagging and printed as `dd.service`, `dd.env` and
+    /// `dd.version`. They can also be set with [`add_static_field`](Self::add_static_field).
+    ///
+    /// With one of the OpenTelemetry features, lines are correlated with traces with
+    /// [`with_datadog_trace_ids`](Self::with_datadog_trace_ids).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use tracing_subscriber::prelude::*;
+    /// let mut layer = json_subscriber::JsonLayer::datadog(std::io::stdout);
+    /// layer.add_static_field("dd.service", "my-service".into());
+    /// # tracing_subscriber::registry().with(layer);
+    /// ```
+    ///
+    /// [Datadog]: https://docs.datadoghq.com/logs/log_configuration/attributes_naming_convention/
+    pub fn datadog<W>(make_writer: W) -> JsonLayer<S, W>
+    where
+        W: for<'writer> MakeWriter<'writer> + 'static,
+    {
+        Self::datadog_with_env(make_writer, |name| std::env::var(name).ok())
+    }
+
+    /// Same as [`datadog`](Self::datadog) but reads the variables with `var` instead of from the
+    /// environment of the process.
+    fn datadog_with_env<W>(make_writer: W, var: impl Fn(&str) -> Option<String>) -> JsonLayer<S, W>
+    where
+        W: for<'writer> MakeWriter<'writer> + 'static,
+    {
+        let mut layer = JsonLayer::new(make_writer);
+        layer
+            .with_timestamp("timestamp", TimestampFormat::default())
+            .with_level("status")
+            .with_target("logger.name")
+            .with_flattened_event()
+            .with_datadog_service_tags_from(var);
+        layer
+    }
+}
+
+impl<S, W> JsonLayer<S, W>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    /// Adds the Datadog unified service tags and trace IDs shared by the Datadog presets of
+    /// `JsonLayer` and `SubscriberBuilder`.
+    pub(crate) fn with_datadog_service_tags(&mut self) -> &mut Self {
+        self.with_datadog_service_tags_from(|name| std::env::var(name).ok())
+    }
+
+    fn with_datadog_service_tags_from(
+        &mut self,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> &mut Self {
+        for (key, name) in [
+            ("dd.service", "DD_SERVICE"),
+            ("dd.env", "DD_ENV"),
+            ("dd.version", "DD_VERSION"),
+        ] {
+            if let Some(value) = var(name) {
+                self.add_cached_field(key.to_owned(), &value);
+            }
+        }
+        #[cfg(feature = "__any-tracing-opentelemetry")]
+        self.with_datadog_trace_ids(true);
+        self
+    }
+}
+
+/// Writes event fields as GELF additional fields and keeps the message aside.
+struct GelfFields<'a, 'w> {
+    writer: &'a mut FieldWriter<'w>,
+    renames: Option<&'a FieldRenames>,
+    key: String,
+    message: Option<String>,
+}
+
+impl GelfFields<'_, '_> {
+    fn write(&mut self, field: &Field, value: impl Serialize) {
+        let name = field.name();
+        let name = name.strip_prefix("r#").unwrap_or(name);
+        let name = self.renames.map_or(name, |renames| renames.rename(name));
+
+        self.key.clear();
+        self.key.push('_');
+        if name == "id" {
+            self.key.push('_');
+        }
+        self.key.extend(name.chars().map(|char| {
+            if char.is_ascii_alphanumeric() || matches!(char, '_' | '.' | '-') {
+                char
+            } else {
+                '_'
+            }
+        }));
+        _ = self.writer.write_field(&self.key, value);
+    }
+}
+
+impl Visit for GelfFields<'_, '_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.write(field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.write(field, value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.write(field, value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.write(field, if value { "true" } else { "false" });
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_owned());
+        } else {
+            self.write(field, value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{value:?}");
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.write(field, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::{registry, Layer};
+
+    use crate::{tests::MockMakeWriter, JsonLayer};
+
+    #[test]
+    fn bunyan() {
+        let make_writer = MockMakeWriter::default();
+        let layer = JsonLayer::bunyan(make_writer.clone(), "test-service");
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::warn!(answer = 42, "hello bunyan");
+        });
+
+        let buf = make_writer.buf();
+        let line = std::str::from_utf8(&buf).unwrap();
+        assert!(line.ends_with('\n'));
+        let line = serde_json::from_str::<Value>(line).unwrap();
+        let line = line.as_object().unwrap();
+
+        // Core fields as documented by Bunyan.
+        assert_eq!(line["v"], 0);
+        assert_eq!(line["level"], 40);
+        assert_eq!(line["name"], "test-service");
+        assert_eq!(line["hostname"], crate::process::hostname_or_unknown());
+        assert_eq!(line["pid"], std::process::id());
+        assert!(line["time"].as_u64().unwrap() > 1_700_000_000_000);
+        assert_eq!(line["msg"], "hello bunyan");
+
+        assert_eq!(line["answer"], 42);
+        assert_eq!(line.len(), 8);
+    }
+
+    #[test]
+    fn bunyan_without_message() {
+        let make_writer = MockMakeWriter::default();
+        let layer = JsonLayer::bunyan(make_writer.clone(), "test-service");
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::info!(answer = 42);
+        });
+
+        let buf = make_writer.buf();
+        let line = serde_json::from_slice::<Value>(&buf).unwrap();
+        assert_eq!(line["msg"], "");
+        assert_eq!(line["answer"], 42);
+        assert_eq!(line.as_object().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn gelf_field_names() {
+        let make_writer = MockMakeWriter::default();
+        let mut layer = JsonLayer::gelf(make_writer.clone());
+        layer.with_field_renames(|name, ()| if name == "user_id" { "id" } else { name }, ());
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::info!(
+                user_id = 1,
+                "http.status-code" = 200,
+                "user name" = "alice",
+                "naïve\"key" = true,
+                "hello gelf"
+            );
+        });
+
+        let buf = make_writer.buf();
+        let line = serde_json::from_slice::<Value>(&buf).unwrap();
+        let line = line.as_object().unwrap();
+
+        assert_eq!(line["__id"], 1);
+        assert_eq!(line["_http.status-code"], 200);
+        assert_eq!(line["_user_name"], "alice");
+        assert_eq!(line["_na_ve_key"], "true");
+        assert!(line.keys().filter(|key| key.starts_with('_')).all(|key| {
+            key != "_id"
+                && key
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || matches!(char, '_' | '.' | '-'))
+        }));
+    }
+
+    #[test]
+    fn datadog() {
+        let make_writer = MockMakeWriter::default();
+        let layer = JsonLayer::datadog_with_env(make_writer.clone(), |name| {
+            match name {
+                "DD_SERVICE" => Some("test-service".to_owned()),
+                "DD_ENV" => Some("test".to_owned()),
+                "DD_VERSION" => Some("1.2.3".to_owned()),
+                _ => None,
+            }
+        });
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::error!(answer = 42, "hello datadog");
+        });
+
+        let buf = make_writer.buf();
+        let line = serde_json::from_slice::<Value>(&buf).unwrap();
+        let line = line.as_object().unwrap();
+
+        assert!(line["timestamp"].is_string());
+        assert_eq!(line["status"], "ERROR");
+        assert_eq!(
+            line["logger.name"],
+            "json_subscriber::layer::presets::tests"
+        );
+        assert_eq!(line["message"], "hello datadog");
+        assert_eq!(line["answer"], 42);
+        assert_eq!(line["dd.service"], "test-service");
+        assert_eq!(line["dd.env"], "test");
+        assert_eq!(line["dd.version"], "1.2.3");
+        // There is no OpenTelemetry span so there are no trace IDs.
+        assert_eq!(line.len(), 8);
+    }
+}
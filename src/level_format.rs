@@ -0,0 +1,177 @@
+use std::fmt;
+
+use tracing_core::Level;
+
+/// Encodings of event levels for
+/// [`JsonLayer::with_level_format`](crate::JsonLayer::with_level_format).
+///
+/// # Examples
+///
+/// ```rust
+/// # use tracing_subscriber::prelude::*;
+/// use json_subscriber::LevelFormat;
+///
+/// let mut layer = json_subscriber::JsonLayer::stdout();
+/// // `"severity":6` for `INFO`
+/// layer.with_level_format("severity", LevelFormat::Syslog);
+/// // `"lvl":"I"` for `INFO`
+/// layer.with_level_format(
+///     "lvl",
+///     LevelFormat::CustomName(|level| &level.as_str()[..1]),
+/// );
+/// # tracing_subscriber::registry().with(layer);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub enum LevelFormat {
+    /// Uppercase names such as `"INFO"`. This is the default.
+    #[default]
+    Uppercase,
+    /// Lowercase names such as `"info"`.
+    Lowercase,
+    /// Numeric [syslog severities], `3` for `ERROR` down to `7` for both `DEBUG` and `TRACE`.
+    ///
+    /// [syslog severities]: https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.1
+    Syslog,
+    /// Numeric levels used by [Bunyan] and pino, `10` for `TRACE` up to `50` for `ERROR`.
+    ///
+    /// [Bunyan]: https://github.com/trentm/node-bunyan#levels
+    Bunyan,
+    /// OpenTelemetry [`SeverityNumber`], `1` for `TRACE` up to `17` for `ERROR`.
+    ///
+    /// [`SeverityNumber`]: https://opentelemetry.io/docs/specs/otel/logs/data-model/#field-severitynumber
+    OpenTelemetry,
+    /// A string returned by the given function.
+    CustomName(fn(&Level) -> &'static str),
+    /// A number returned by the given function.
+    CustomNumber(fn(&Level) -> i64),
+}
+
+impl LevelFormat {
+    pub(crate) fn write(self, writer: &mut dyn fmt::Write, level: Level) -> fmt::Result {
+        match self {
+            Self::Uppercase => write!(writer, "\"{}\"", level.as_str()),
+            Self::Lowercase => write!(writer, "\"{}\"", lowercase_name(level)),
+            Self::Syslog => write!(writer, "{}", syslog_severity(level)),
+            Self::Bunyan => write!(writer, "{}", bunyan_level(level)),
+            Self::OpenTelemetry => write!(writer, "{}", opentelemetry_severity(level)),
+            Self::CustomName(name) => crate::layer::write_escaped(writer, name(&level)),
+            Self::CustomNumber(number) => write!(writer, "{}", number(&level)),
+        }
+    }
+}
+
+fn lowercase_name(level: Level) -> &'static str {
+    match level {
+        Level::TRACE => "trace",
+        Level::DEBUG => "debug",
+        Level::INFO => "info",
+        Level::WARN => "warn",
+        Level::ERROR => "error",
+    }
+}
+
+pub(crate) fn syslog_severity(level: Level) -> u8 {
+    match level {
+        Level::TRACE | Level::DEBUG => 7,
+        Level::INFO => 6,
+        Level::WARN => 4,
+        Level::ERROR => 3,
+    }
+}
+
+pub(crate) fn bunyan_level(level: Level) -> u8 {
+    match level {
+        Level::TRACE => 10,
+        Level::DEBUG => 20,
+        Level::INFO => 30,
+        Level::WARN => 40,
+        Level::ERROR => 50,
+    }
+}
+
+pub(crate) fn opentelemetry_severity(level: Level) -> u8 {
+    match level {
+        Level::TRACE => 1,
+        Level::DEBUG => 5,
+        Level::INFO => 9,
+        Level::WARN => 13,
+        Level::ERROR => 17,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_core::Level;
+
+    use super::LevelFormat;
+
+    fn format(format: LevelFormat) -> Vec<String> {
+        [
+            Level::TRACE,
+            Level::DEBUG,
+            Level::INFO,
+            Level::WARN,
+            Level::ERROR,
+        ]
+        .iter()
+        .map(|level| {
+            let mut output = String::new();
+            format.write(&mut output, *level).unwrap();
+            output
+        })
+        .collect()
+    }
+
+    #[test]
+    fn level_formats() {
+        assert_eq!(
+            format(LevelFormat::Uppercase),
+            [
+                "\"TRACE\"",
+                "\"DEBUG\"",
+                "\"INFO\"",
+                "\"WARN\"",
+                "\"ERROR\""
+            ]
+        );
+        assert_eq!(
+            format(LevelFormat::Lowercase),
+            [
+                "\"trace\"",
+                "\"debug\"",
+                "\"info\"",
+                "\"warn\"",
+                "\"error\""
+            ]
+        );
+        assert_eq!(format(LevelFormat::Syslog), ["7", "7", "6", "4", "3"]);
+        assert_eq!(format(LevelFormat::Bunyan), ["10", "20", "30", "40", "50"]);
+        assert_eq!(
+            format(LevelFormat::OpenTelemetry),
+            ["1", "5", "9", "13", "17"]
+        );
+        assert_eq!(
+            format(LevelFormat::CustomName(|level| {
+                if *level >= Level::INFO {
+                    "quiet \"ok\""
+                } else {
+                    "loud"
+                }
+            })),
+            [
+                "\"quiet \\\"ok\\\"\"",
+                "\"quiet \\\"ok\\\"\"",
+                "\"quiet \\\"ok\\\"\"",
+                "\"loud\"",
+                "\"loud\""
+            ]
+        );
+        assert_eq!(
+            format(LevelFormat::CustomNumber(|level| {
+                -i64::from(super::bunyan_level(*level))
+            })),
+            ["-10", "-20", "-30", "-40", "-50"]
+        );
+    }
+}
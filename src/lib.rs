@@ -109,8 +109,14 @@ mod field_writer;
 mod fields;
 pub mod fmt;
 mod layer;
+mod level_format;
+mod process;
 mod serde;
+mod span_format;
+mod span_selection;
+mod timestamp;
 mod visitor;
+pub mod writer;
 
 #[cfg(test)]
 mod tests;
@@ -118,3 +124,8 @@ mod tests;
 pub use field_writer::FieldWriter;
 pub use fmt::{fmt, layer};
 pub use layer::JsonLayer;
+pub use level_format::LevelFormat;
+pub use process::CrateInfo;
+pub use span_format::SpanFormat;
+pub use span_selection::SpanSelection;
+pub use timestamp::{Rfc3339, TimestampFormat};
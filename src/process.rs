@@ -0,0 +1,82 @@
+/// Name and version of a crate, usually the binary that is logging.
+///
+/// Use the [`crate_info!`](crate::crate_info) macro to get the info of the crate calling it and
+/// pass it to [`JsonLayer::with_crate_info`](crate::JsonLayer::with_crate_info) or
+/// [`SubscriberBuilder::with_crate_info`](crate::fmt::SubscriberBuilder::with_crate_info).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrateInfo {
+    name: &'static str,
+    version: &'static str,
+}
+
+impl CrateInfo {
+    /// Creates the info from a name and a version.
+    #[must_use]
+    pub const fn new(name: &'static str, version: &'static str) -> Self {
+        Self { name, version }
+    }
+
+    /// Returns the name of the crate.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the version of the crate.
+    #[must_use]
+    pub const fn version(&self) -> &'static str {
+        self.version
+    }
+}
+
+/// Returns the [`CrateInfo`] of the crate where this macro is called, as set by Cargo.
+///
+/// # Examples
+///
+/// ```rust
+/// # use tracing_subscriber::prelude::*;
+/// let mut layer = json_subscriber::JsonLayer::stdout();
+/// layer.with_crate_info("service", "version", json_subscriber::crate_info!());
+/// # tracing_subscriber::registry().with(layer);
+/// ```
+#[macro_export]
+macro_rules! crate_info {
+    () => {
+        $crate::CrateInfo::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    };
+}
+
+/// Returns the file name of the current executable without any directories.
+pub(crate) fn executable_name() -> Option<String> {
+    let path = std::env::current_exe()
+        .ok()
+        .or_else(|| std::env::args_os().next().map(Into::into))?;
+    path.file_name()?.to_str().map(ToOwned::to_owned)
+}
+
+/// Returns the hostname of the machine or `"unknown"` if it cannot be found, for formats which
+/// require a hostname.
+pub(crate) fn hostname_or_unknown() -> String {
+    hostname().unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Returns the hostname of the machine as reported by the operating system.
+#[cfg(feature = "hostname")]
+pub(crate) fn hostname() -> Option<String> {
+    gethostname::gethostname().into_string().ok()
+}
+
+/// Returns the hostname of the machine from the `HOSTNAME` or `COMPUTERNAME` environment variable
+/// or from `/etc/hostname`.
+#[cfg(not(feature = "hostname"))]
+pub(crate) fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|hostname| hostname.trim().to_owned())
+        })
+        .filter(|hostname| !hostname.is_empty())
+}
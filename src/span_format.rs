@@ -0,0 +1,258 @@
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use tracing_core::Metadata;
+
+use crate::fields::{FieldRenames, JsonFields, JsonFieldsInner, SpanIds};
+
+/// Configures how spans are represented by [`JsonLayer::with_current_span`] and
+/// [`JsonLayer::with_span_list`].
+///
+/// The default is the [`flat`](Self::flat) representation which mixes the span's name into its
+/// fields, for example `{"name":"my_span","answer":42}`. This is compatible with
+/// `tracing-subscriber`.
+///
+/// The [`nested`](Self::nested) representation separates span metadata from the fields, for
+/// example `{"name":"my_span","target":"my_crate","fields":{"answer":42}}`.
+///
+/// Which metadata is included can be chosen with the other methods. The span's name is always
+/// included.
+///
+/// # Examples
+///
+/// ```rust
+/// # use tracing_subscriber::prelude::*;
+/// use json_subscriber::SpanFormat;
+///
+/// let mut layer = json_subscriber::JsonLayer::stdout();
+/// layer
+///     .with_span_list("spans")
+///     .with_span_format(SpanFormat::nested().with_target(true).with_level(true));
+/// # tracing_subscriber::registry().with(layer);
+/// ```
+///
+/// [`JsonLayer::with_current_span`]: crate::JsonLayer::with_current_span
+/// [`JsonLayer::with_span_list`]: crate::JsonLayer::with_span_list
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SpanFormat {
+    nest_fields: bool,
+    display_target: bool,
+    display_level: bool,
+    display_filename: bool,
+    display_line_number: bool,
+    display_id: bool,
+    display_parent_id: bool,
+    display_follows_from: bool,
+}
+
+impl SpanFormat {
+    /// Spans are printed as a single object with the span's fields and its name under the `name`
+    /// key. Any enabled metadata are added next to the name.
+    ///
+    /// The metadata take precedence over fields with the same key so a field called `name` can
+    /// never overwrite the span's name. Such fields are omitted from the output.
+    ///
+    /// Field renames set by [`JsonLayer::with_field_renames`](crate::JsonLayer::with_field_renames)
+    /// apply to the fields and the `name` key, not to the other metadata keys.
+    #[must_use]
+    pub fn flat() -> Self {
+        Self::default()
+    }
+
+    /// Spans are printed as an object with the span's name, any enabled metadata and an object
+    /// with the span's fields under the `fields` key.
+    ///
+    /// Field renames set by [`JsonLayer::with_field_renames`](crate::JsonLayer::with_field_renames)
+    /// apply to the fields and the `name` key, not to the other metadata keys.
+    #[must_use]
+    pub fn nested() -> Self {
+        Self {
+            nest_fields: true,
+            ..Self::default()
+        }
+    }
+
+    /// Sets whether or not the span's target is displayed. It will use the `target` key if so.
+    #[must_use]
+    pub fn with_target(self, display_target: bool) -> Self {
+        Self {
+            display_target,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the span's level is displayed. It will use the `level` key if so.
+    #[must_use]
+    pub fn with_level(self, display_level: bool) -> Self {
+        Self {
+            display_level,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the span's [source code file path][file] is displayed. It will use the
+    /// `file` key if so.
+    ///
+    /// [file]: tracing_core::Metadata::file
+    #[must_use]
+    pub fn with_file(self, display_filename: bool) -> Self {
+        Self {
+            display_filename,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the span's [source code line number][line] is displayed. It will use
+    /// the `line` key if so.
+    ///
+    /// [line]: tracing_core::Metadata::line
+    #[must_use]
+    pub fn with_line_number(self, display_line_number: bool) -> Self {
+        Self {
+            display_line_number,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the span's [ID](tracing_core::span::Id) is displayed. It will use the
+    /// `id` key if so.
+    #[must_use]
+    pub fn with_id(self, display_id: bool) -> Self {
+        Self { display_id, ..self }
+    }
+
+    /// Sets whether or not the [ID](tracing_core::span::Id) of the span's parent is displayed. It
+    /// will use the `parentId` key if so. The value is `null` for root spans.
+    #[must_use]
+    pub fn with_parent_id(self, display_parent_id: bool) -> Self {
+        Self {
+            display_parent_id,
+            ..self
+        }
+    }
+
+    /// Sets whether or not the [IDs](tracing_core::span::Id) of spans this span
+    /// [follows from](tracing::Span::follows_from) are displayed. It will use the `followsFrom` key
+    /// if so and the value will be an array of the IDs.
+    #[must_use]
+    pub fn with_follows_from(self, display_follows_from: bool) -> Self {
+        Self {
+            display_follows_from,
+            ..self
+        }
+    }
+
+    fn metadata_entries(
+        &self,
+        metadata: &'static Metadata<'static>,
+        ids: &SpanIds,
+    ) -> impl Iterator<Item = (&'static str, serde_json::Value)> + Clone {
+        [
+            Some(("name", metadata.name().into())),
+            self.display_target
+                .then(|| ("target", metadata.target().into())),
+            self.display_level
+                .then(|| ("level", metadata.level().as_str().into())),
+            self.display_filename
+                .then(|| ("file", metadata.file().into())),
+            self.display_line_number
+                .then(|| ("line", metadata.line().into())),
+            self.display_id.then(|| ("id", ids.id.into())),
+            self.display_parent_id
+                .then(|| ("parentId", ids.parent.into())),
+            self.display_follows_from
+                .then(|| ("followsFrom", ids.follows_from.clone().into())),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Creates the span extension with the serialized span from recorded fields.
+    pub(crate) fn finish(
+        &self,
+        inner: JsonFieldsInner,
+        metadata: &'static Metadata<'static>,
+        ids: SpanIds,
+        renames: Option<&FieldRenames>,
+    ) -> JsonFields {
+        let mut fields = JsonFields {
+            inner,
+            metadata,
+            ids,
+            serialized: Arc::from(""),
+            serialized_fields: Arc::from(""),
+        };
+        self.update(&mut fields, renames);
+        fields
+    }
+
+    /// Serializes the span again after its fields have changed.
+    pub(crate) fn update(&self, fields: &mut JsonFields, renames: Option<&FieldRenames>) {
+        let name_key = renames.map_or(Cow::Borrowed("name"), |renames| {
+            renames.rename_static("name")
+        });
+        if self.nest_fields {
+            let serialized_fields = serde_json::to_string(&fields.inner.fields).unwrap();
+            let serialized = serde_json::to_string(&NestedSpan {
+                name_key: &name_key,
+                entries: self.metadata_entries(fields.metadata, &fields.ids),
+                fields: &fields.inner.fields,
+            })
+            .unwrap();
+            fields.serialized = Arc::from(serialized.as_str());
+            fields.serialized_fields = Arc::from(serialized_fields.as_str());
+        } else {
+            // The name is kept with the fields so that flattened span fields include it, as they
+            // always have. The other metadata are only merged when serializing the whole span so
+            // that they do not appear among the flattened span fields.
+            fields
+                .inner
+                .fields
+                .insert(name_key, fields.metadata.name().into());
+            let serialized_fields = serde_json::to_string(&fields.inner.fields).unwrap();
+
+            let entries = self
+                .metadata_entries(fields.metadata, &fields.ids)
+                .filter(|(key, _)| *key != "name")
+                .collect::<Vec<_>>();
+            // Metadata are inserted after the fields so they always win.
+            let mut merged = fields
+                .inner
+                .fields
+                .iter()
+                .map(|(key, value)| (key.as_ref(), value))
+                .collect::<BTreeMap<_, _>>();
+            for (key, value) in &entries {
+                merged.insert(key, value);
+            }
+            let serialized = serde_json::to_string(&merged).unwrap();
+            fields.serialized = Arc::from(serialized.as_str());
+            fields.serialized_fields = Arc::from(serialized_fields.as_str());
+        }
+    }
+}
+
+struct NestedSpan<'a, I> {
+    /// The possibly renamed key of the span's name.
+    name_key: &'a str,
+    entries: I,
+    fields: &'a BTreeMap<Cow<'static, str>, serde_json::Value>,
+}
+
+impl<I> Serialize for NestedSpan<'_, I>
+where
+    I: Iterator<Item = (&'static str, serde_json::Value)> + Clone,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut serializer = serializer.serialize_map(None)?;
+        for (key, value) in self.entries.clone() {
+            let key = if key == "name" { self.name_key } else { key };
+            serializer.serialize_entry(key, &value)?;
+        }
+        serializer.serialize_entry("fields", self.fields)?;
+        serializer.end()
+    }
+}
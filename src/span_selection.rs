@@ -0,0 +1,143 @@
+use std::borrow::Cow;
+
+use tracing_core::{LevelFilter, Metadata};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+/// Selects which spans are printed in span lists such as
+/// [`JsonLayer::with_selected_span_list`](crate::JsonLayer::with_selected_span_list).
+///
+/// By default all spans are selected. Spans can be filtered by their target, name or level. If
+/// multiple target prefixes or names are given, a span needs to match only one of them. A span must
+/// match all kinds of filters to be selected.
+///
+/// The number of spans can also be limited to the ones closest to the event or closest to the root
+/// span. The limit is applied after filtering.
+///
+/// # Examples
+///
+/// Print at most three spans from `my_crate` closest to the event:
+///
+/// ```rust
+/// # use tracing_subscriber::prelude::*;
+/// use json_subscriber::SpanSelection;
+///
+/// let mut layer = json_subscriber::JsonLayer::stdout();
+/// layer.with_selected_span_list(
+///     "spans",
+///     SpanSelection::new()
+///         .with_target_prefix("my_crate")
+///         .innermost(3),
+/// );
+/// # tracing_subscriber::registry().with(layer);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SpanSelection {
+    limit: Option<SpanLimit>,
+    target_prefixes: Vec<Cow<'static, str>>,
+    names: Vec<Cow<'static, str>>,
+    max_level: Option<LevelFilter>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SpanLimit {
+    Innermost(usize),
+    Outermost(usize),
+}
+
+impl SpanSelection {
+    /// Creates a selection of all spans.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps only the given number of selected spans closest to the event.
+    ///
+    /// This overrides any previous calls to [`outermost`](Self::outermost).
+    #[must_use]
+    pub fn innermost(self, count: usize) -> Self {
+        Self {
+            limit: Some(SpanLimit::Innermost(count)),
+            ..self
+        }
+    }
+
+    /// Keeps only the given number of selected spans closest to the root span.
+    ///
+    /// This overrides any previous calls to [`innermost`](Self::innermost).
+    #[must_use]
+    pub fn outermost(self, count: usize) -> Self {
+        Self {
+            limit: Some(SpanLimit::Outermost(count)),
+            ..self
+        }
+    }
+
+    /// Selects spans whose target starts with the given prefix.
+    #[must_use]
+    pub fn with_target_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.target_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Selects spans with the given name.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.names.push(name.into());
+        self
+    }
+
+    /// Selects spans with the given level or any more severe level. For example
+    /// [`Level::INFO`](tracing_core::Level::INFO) selects `INFO`, `WARN` and `ERROR` spans.
+    #[must_use]
+    pub fn with_max_level(self, max_level: impl Into<LevelFilter>) -> Self {
+        Self {
+            max_level: Some(max_level.into()),
+            ..self
+        }
+    }
+
+    fn matches(&self, metadata: &Metadata<'_>) -> bool {
+        let target = metadata.target();
+        (self.target_prefixes.is_empty()
+            || self
+                .target_prefixes
+                .iter()
+                .any(|prefix| target.starts_with(prefix.as_ref())))
+            && (self.names.is_empty() || self.names.iter().any(|name| name == metadata.name()))
+            && self
+                .max_level
+                .map_or(true, |max_level| max_level >= *metadata.level())
+    }
+
+    /// Calls `f` with each selected span in the scope of `leaf` in the order from the root span to
+    /// the leaf.
+    pub(crate) fn for_each<'a, S>(&self, leaf: &SpanRef<'a, S>, mut f: impl FnMut(SpanRef<'a, S>))
+    where
+        S: for<'lookup> LookupSpan<'lookup>,
+    {
+        let scope = leaf.scope();
+        match self.limit {
+            None => {
+                scope
+                    .from_root()
+                    .filter(|span| self.matches(span.metadata()))
+                    .for_each(f);
+            },
+            Some(SpanLimit::Outermost(count)) => {
+                scope
+                    .from_root()
+                    .filter(|span| self.matches(span.metadata()))
+                    .take(count)
+                    .for_each(f);
+            },
+            Some(SpanLimit::Innermost(count)) => {
+                let selected = scope
+                    .filter(|span| self.matches(span.metadata()))
+                    .take(count)
+                    .collect::<Vec<_>>();
+                selected.into_iter().rev().for_each(&mut f);
+            },
+        }
+    }
+}
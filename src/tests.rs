@@ -1,6 +1,8 @@
 use std::{
     fmt,
     io,
+    ops::Deref,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex, MutexGuard, TryLockError},
 };
 
@@ -57,6 +59,35 @@ impl<'a> MakeWriter<'a> for MockMakeWriter {
     }
 }
 
+/// A unique path for a socket in the temporary directory, removed again when dropped.
+pub(crate) struct TempSocketPath(PathBuf);
+
+impl TempSocketPath {
+    pub(crate) fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("json-subscriber-{}.sock", uuid::Uuid::new_v4())))
+    }
+}
+
+impl Deref for TempSocketPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempSocketPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempSocketPath {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.0);
+    }
+}
+
 pub(crate) struct MockTime;
 impl FormatTime for MockTime {
     fn format_time(&self, w: &mut Writer<'_>) -> fmt::Result {
@@ -0,0 +1,321 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Built-in encodings of event timestamps for
+/// [`JsonLayer::with_timestamp`](crate::JsonLayer::with_timestamp).
+///
+/// Unlike [`FormatTime`](tracing_subscriber::fmt::time::FormatTime) implementations which always
+/// produce a string, these can also produce JSON numbers and objects. They are written directly
+/// into the output without any intermediate allocations.
+///
+/// # Examples
+///
+/// ```rust
+/// # use tracing_subscriber::prelude::*;
+/// use json_subscriber::{Rfc3339, TimestampFormat};
+///
+/// let mut layer = json_subscriber::JsonLayer::stdout();
+/// // `"ts":1718000000123`
+/// layer.with_timestamp("ts", TimestampFormat::UnixMillis);
+/// // `"time":"2024-06-10T08:13:20.123+02:00"`
+/// layer.with_timestamp(
+///     "time",
+///     TimestampFormat::Rfc3339(Rfc3339::new().with_precision(3).with_offset_minutes(120)),
+/// );
+/// # tracing_subscriber::registry().with(layer);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimestampFormat {
+    /// A string with the date and time as described in [RFC 3339], for example
+    /// `"2024-06-10T06:13:20.123456Z"`.
+    ///
+    /// [RFC 3339]: https://datatracker.ietf.org/doc/html/rfc3339
+    Rfc3339(Rfc3339),
+    /// Whole seconds since the Unix epoch as a number, for example `1718000000`.
+    UnixSeconds,
+    /// Seconds since the Unix epoch as a number with nanosecond precision, for example
+    /// `1718000000.123456789`.
+    UnixSecondsFractional,
+    /// Milliseconds since the Unix epoch as a number, for example `1718000000123`.
+    UnixMillis,
+    /// Microseconds since the Unix epoch as a number, for example `1718000000123456`.
+    UnixMicros,
+    /// Nanoseconds since the Unix epoch as a number, for example `1718000000123456789`.
+    UnixNanos,
+    /// An object with whole seconds since the Unix epoch and the nanoseconds within the second,
+    /// for example `{"seconds":1718000000,"nanos":123456789}`. This is the format used by
+    /// Google Cloud Logging.
+    SecondsAndNanos,
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        Self::Rfc3339(Rfc3339::default())
+    }
+}
+
+/// Configuration of the [`TimestampFormat::Rfc3339`] format.
+///
+/// By default, timestamps are in UTC with microsecond precision which is the same as the default
+/// timer of `tracing-subscriber`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rfc3339 {
+    precision: u8,
+    offset_minutes: i16,
+}
+
+impl Default for Rfc3339 {
+    fn default() -> Self {
+        Self {
+            precision: 6,
+            offset_minutes: 0,
+        }
+    }
+}
+
+impl Rfc3339 {
+    /// Creates the default configuration with microsecond precision in UTC.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of digits of the fractional seconds. Zero omits the fraction completely and
+    /// values larger than 9 are treated as 9.
+    #[must_use]
+    pub fn with_precision(self, digits: u8) -> Self {
+        Self {
+            precision: digits.min(9),
+            ..self
+        }
+    }
+
+    /// Sets a fixed offset from UTC in minutes. The time will be shifted by this offset and the
+    /// offset will be written instead of `Z`, for example `+02:00`.
+    ///
+    /// Offsets are clamped to less than a day.
+    #[must_use]
+    pub fn with_offset_minutes(self, offset_minutes: i16) -> Self {
+        Self {
+            offset_minutes: offset_minutes.clamp(-1439, 1439),
+            ..self
+        }
+    }
+
+    fn write(self, writer: &mut dyn fmt::Write, secs: i64, nanos: u32) -> fmt::Result {
+        let secs = secs + i64::from(self.offset_minutes) * 60;
+        let days = secs.div_euclid(86_400);
+        let seconds_of_day = secs.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+
+        write!(
+            writer,
+            "\"{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            seconds_of_day / 3600,
+            seconds_of_day / 60 % 60,
+            seconds_of_day % 60,
+        )?;
+
+        if self.precision > 0 {
+            let digits = usize::from(self.precision);
+            let fraction = nanos / 10u32.pow(9 - u32::from(self.precision));
+            write!(writer, ".{fraction:0digits$}")?;
+        }
+
+        if self.offset_minutes == 0 {
+            writer.write_str("Z\"")
+        } else {
+            let sign = if self.offset_minutes < 0 { '-' } else { '+' };
+            let offset = self.offset_minutes.unsigned_abs();
+            write!(writer, "{sign}{:02}:{:02}\"", offset / 60, offset % 60)
+        }
+    }
+}
+
+impl TimestampFormat {
+    pub(crate) fn write_now(self, writer: &mut dyn fmt::Write) -> fmt::Result {
+        self.write(writer, SystemTime::now())
+    }
+
+    pub(crate) fn write(self, writer: &mut dyn fmt::Write, time: SystemTime) -> fmt::Result {
+        // Seconds are rounded down so that nanoseconds are always positive.
+        let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => (duration_secs(duration.as_secs()), duration.subsec_nanos()),
+            Err(error) => {
+                let duration = error.duration();
+                let secs = -duration_secs(duration.as_secs());
+                match duration.subsec_nanos() {
+                    0 => (secs, 0),
+                    nanos => (secs - 1, 1_000_000_000 - nanos),
+                }
+            },
+        };
+
+        match self {
+            Self::Rfc3339(rfc3339) => rfc3339.write(writer, secs, nanos),
+            Self::UnixSeconds => write!(writer, "{secs}"),
+            Self::UnixSecondsFractional => {
+                if secs < 0 && nanos > 0 {
+                    write!(writer, "-{}.{:09}", -(secs + 1), 1_000_000_000 - nanos)
+                } else {
+                    write!(writer, "{secs}.{nanos:09}")
+                }
+            },
+            Self::UnixMillis => {
+                write!(
+                    writer,
+                    "{}",
+                    i128::from(secs) * 1_000 + i128::from(nanos / 1_000_000)
+                )
+            },
+            Self::UnixMicros => {
+                write!(
+                    writer,
+                    "{}",
+                    i128::from(secs) * 1_000_000 + i128::from(nanos / 1_000)
+                )
+            },
+            Self::UnixNanos => {
+                write!(
+                    writer,
+                    "{}",
+                    i128::from(secs) * 1_000_000_000 + i128::from(nanos)
+                )
+            },
+            Self::SecondsAndNanos => write!(writer, "{{\"seconds\":{secs},\"nanos\":{nanos}}}"),
+        }
+    }
+}
+
+fn duration_secs(secs: u64) -> i64 {
+    i64::try_from(secs).unwrap_or(i64::MAX)
+}
+
+/// Converts days since the Unix epoch to a date in the proleptic Gregorian calendar.
+///
+/// This is the `civil_from_days` algorithm by Howard Hinnant.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::{Rfc3339, TimestampFormat};
+
+    fn format(format: TimestampFormat, time: SystemTime) -> String {
+        let mut output = String::new();
+        format.write(&mut output, time).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&output).is_ok());
+        output
+    }
+
+    #[test]
+    fn rfc3339() {
+        let time = UNIX_EPOCH + Duration::new(1_718_000_000, 123_456_789);
+
+        assert_eq!(
+            format(TimestampFormat::default(), time),
+            "\"2024-06-10T06:13:20.123456Z\""
+        );
+        assert_eq!(
+            format(
+                TimestampFormat::Rfc3339(Rfc3339::new().with_precision(0)),
+                time
+            ),
+            "\"2024-06-10T06:13:20Z\""
+        );
+        assert_eq!(
+            format(
+                TimestampFormat::Rfc3339(Rfc3339::new().with_precision(12)),
+                time
+            ),
+            "\"2024-06-10T06:13:20.123456789Z\""
+        );
+        assert_eq!(
+            format(
+                TimestampFormat::Rfc3339(
+                    Rfc3339::new()
+                        .with_precision(3)
+                        .with_offset_minutes(-(7 * 60 + 30))
+                ),
+                time
+            ),
+            "\"2024-06-09T22:43:20.123-07:30\""
+        );
+        assert_eq!(
+            format(
+                TimestampFormat::Rfc3339(Rfc3339::new().with_offset_minutes(60)),
+                UNIX_EPOCH + Duration::from_secs(951_778_800),
+            ),
+            "\"2000-02-29T00:00:00.000000+01:00\""
+        );
+        assert_eq!(
+            format(
+                TimestampFormat::default(),
+                UNIX_EPOCH - Duration::from_millis(1)
+            ),
+            "\"1969-12-31T23:59:59.999000Z\""
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        let time = UNIX_EPOCH + Duration::new(1_718_000_000, 123_456_789);
+
+        assert_eq!(format(TimestampFormat::UnixSeconds, time), "1718000000");
+        assert_eq!(
+            format(TimestampFormat::UnixSecondsFractional, time),
+            "1718000000.123456789"
+        );
+        assert_eq!(format(TimestampFormat::UnixMillis, time), "1718000000123");
+        assert_eq!(
+            format(TimestampFormat::UnixMicros, time),
+            "1718000000123456"
+        );
+        assert_eq!(
+            format(TimestampFormat::UnixNanos, time),
+            "1718000000123456789"
+        );
+        assert_eq!(
+            format(TimestampFormat::SecondsAndNanos, time),
+            "{\"seconds\":1718000000,\"nanos\":123456789}"
+        );
+    }
+
+    #[test]
+    fn before_epoch() {
+        let time = UNIX_EPOCH - Duration::new(1, 250_000_000);
+
+        assert_eq!(format(TimestampFormat::UnixSeconds, time), "-2");
+        assert_eq!(
+            format(TimestampFormat::UnixSecondsFractional, time),
+            "-1.250000000"
+        );
+        assert_eq!(format(TimestampFormat::UnixMillis, time), "-1250");
+        assert_eq!(
+            format(TimestampFormat::SecondsAndNanos, time),
+            "{\"seconds\":-2,\"nanos\":750000000}"
+        );
+    }
+}
@@ -1,18 +1,26 @@
-use std::{collections::btree_map::Entry, fmt};
+use std::{borrow::Cow, collections::btree_map::Entry, fmt};
 
 use tracing_core::field;
 
-use crate::fields::JsonFieldsInner;
+use crate::fields::{FieldRenames, JsonFieldsInner};
 
 /// The [visitor] produced by [`JsonFields`]'s [`MakeVisitor`] implementation.
 ///
 /// [visitor]: tracing_subscriber::field::Visit
 /// [`MakeVisitor`]: tracing_subscriber::field::MakeVisitor
-pub(crate) struct JsonVisitor<'a>(&'a mut JsonFieldsInner);
+pub(crate) struct JsonVisitor<'a>(&'a mut JsonFieldsInner, Option<&'a FieldRenames>);
 
 impl<'a> JsonVisitor<'a> {
-    pub fn new(fields: &'a mut JsonFieldsInner) -> Self {
-        Self(fields)
+    pub fn new(fields: &'a mut JsonFieldsInner, renames: Option<&'a FieldRenames>) -> Self {
+        Self(fields, renames)
+    }
+
+    /// Returns the key under which a field with the given name is stored.
+    fn key(&self, name: &'static str) -> Cow<'static, str> {
+        match self.1 {
+            Some(renames) => renames.rename_static(name),
+            None => Cow::Borrowed(name),
+        }
     }
 }
 
@@ -20,7 +28,7 @@ impl field::Visit for JsonVisitor<'_> {
     /// Visit a double precision floating point value.
     fn record_f64(&mut self, field: &field::Field, value: f64) {
         let value = serde_json::Value::from(value);
-        let entry = self.0.fields.entry(field.name());
+        let entry = self.0.fields.entry(self.key(field.name()));
         match entry {
             Entry::Vacant(vacant) => {
                 self.0.version += 1;
@@ -38,7 +46,7 @@ impl field::Visit for JsonVisitor<'_> {
     /// Visit a signed 64-bit integer value.
     fn record_i64(&mut self, field: &field::Field, value: i64) {
         let value = serde_json::Value::from(value);
-        let entry = self.0.fields.entry(field.name());
+        let entry = self.0.fields.entry(self.key(field.name()));
         match entry {
             Entry::Vacant(vacant) => {
                 self.0.version += 1;
@@ -56,7 +64,7 @@ impl field::Visit for JsonVisitor<'_> {
     /// Visit an unsigned 64-bit integer value.
     fn record_u64(&mut self, field: &field::Field, value: u64) {
         let value = serde_json::Value::from(value);
-        let entry = self.0.fields.entry(field.name());
+        let entry = self.0.fields.entry(self.key(field.name()));
         match entry {
             Entry::Vacant(vacant) => {
                 self.0.version += 1;
@@ -74,7 +82,7 @@ impl field::Visit for JsonVisitor<'_> {
     /// Visit a boolean value.
     fn record_bool(&mut self, field: &field::Field, value: bool) {
         let value = serde_json::Value::from(value);
-        let entry = self.0.fields.entry(field.name());
+        let entry = self.0.fields.entry(self.key(field.name()));
         match entry {
             Entry::Vacant(vacant) => {
                 self.0.version += 1;
@@ -94,7 +102,7 @@ impl field::Visit for JsonVisitor<'_> {
         // We don't want to clone the `value` until we know we want to update it
         // so this closure is here to defer the actual value creation.
         let serde_value = || serde_json::Value::from(value);
-        let entry = self.0.fields.entry(field.name());
+        let entry = self.0.fields.entry(self.key(field.name()));
         match entry {
             Entry::Vacant(vacant) => {
                 self.0.version += 1;
@@ -115,14 +123,16 @@ impl field::Visit for JsonVisitor<'_> {
             #[cfg(feature = "tracing-log")]
             name if name.starts_with("log.") => (),
             name if name.starts_with("r#") => {
+                let key = self.key(&name[2..]);
                 self.0
                     .fields
-                    .insert(&name[2..], serde_json::Value::from(format!("{value:?}")));
+                    .insert(key, serde_json::Value::from(format!("{value:?}")));
             },
             name => {
+                let key = self.key(name);
                 self.0
                     .fields
-                    .insert(name, serde_json::Value::from(format!("{value:?}")));
+                    .insert(key, serde_json::Value::from(format!("{value:?}")));
             },
         }
     }
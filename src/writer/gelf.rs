@@ -0,0 +1,295 @@
+use std::{
+    io,
+    net::{ToSocketAddrs, UdpSocket},
+};
+
+#[cfg(feature = "gelf-compression")]
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const CHUNK_HEADER_LEN: usize = 12;
+const MAX_CHUNKS: usize = 128;
+
+/// Compression of GELF messages sent by [`GelfUdpWriter`].
+#[cfg(feature = "gelf-compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gelf-compression")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GelfCompression {
+    /// Compress messages with zlib.
+    Zlib,
+    /// Compress messages with gzip.
+    Gzip,
+}
+
+/// A [`MakeWriter`] sending each written message as a [GELF] UDP datagram, split into GELF chunks
+/// if it is larger than the chunk size.
+///
+/// Each message is [one write](crate::writer#one-write-per-line). This is meant to be used with
+/// [`JsonLayer::gelf`](crate::JsonLayer::gelf).
+///
+/// [GELF]: https://go2docs.graylog.org/current/getting_in_log_data/gelf.html
+#[derive(Debug)]
+pub struct GelfUdpWriter {
+    socket: UdpSocket,
+    chunk_size: usize,
+    #[cfg(feature = "gelf-compression")]
+    compression: Option<GelfCompression>,
+}
+
+impl GelfUdpWriter {
+    /// The default size of datagrams, which should fit into a single packet on most networks.
+    pub const DEFAULT_CHUNK_SIZE: usize = 1420;
+
+    /// Creates a writer sending messages to the given address from an ephemeral local port.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be resolved or a socket cannot be bound.
+    pub fn new(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            socket: super::connect_udp(address)?,
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            #[cfg(feature = "gelf-compression")]
+            compression: None,
+        })
+    }
+
+    /// Sets the maximum size of a datagram including the chunk header. Messages larger than this
+    /// are split into chunks. The size is clamped to at least 13 bytes.
+    #[must_use]
+    pub fn with_chunk_size(self, chunk_size: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(CHUNK_HEADER_LEN + 1),
+            ..self
+        }
+    }
+
+    /// Compresses messages before they are chunked.
+    #[cfg(feature = "gelf-compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "gelf-compression")))]
+    #[must_use]
+    pub fn with_compression(self, compression: GelfCompression) -> Self {
+        Self {
+            compression: Some(compression),
+            ..self
+        }
+    }
+
+    fn send(&self, message: &[u8]) -> io::Result<()> {
+        let message = super::strip_newline(message);
+
+        #[cfg(feature = "gelf-compression")]
+        let compressed;
+        #[cfg(feature = "gelf-compression")]
+        let message = match self.compression {
+            Some(compression) => {
+                compressed = compress(message, compression)?;
+                &compressed[..]
+            },
+            None => message,
+        };
+
+        if message.len() <= self.chunk_size {
+            self.socket.send(message)?;
+            return Ok(());
+        }
+
+        let chunks = message.chunks(self.chunk_size - CHUNK_HEADER_LEN);
+        let count = chunks.len();
+        if count > MAX_CHUNKS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("GELF message needs {count} chunks but at most {MAX_CHUNKS} are allowed"),
+            ));
+        }
+
+        let id = uuid::Uuid::new_v4();
+        let mut datagram = Vec::with_capacity(self.chunk_size);
+        for (sequence, chunk) in chunks.enumerate() {
+            datagram.clear();
+            datagram.extend_from_slice(&CHUNK_MAGIC);
+            datagram.extend_from_slice(&id.as_bytes()[..8]);
+            #[allow(clippy::cast_possible_truncation)]
+            datagram.extend_from_slice(&[sequence as u8, count as u8]);
+            datagram.extend_from_slice(chunk);
+            self.socket.send(&datagram)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "gelf-compression")]
+fn compress(message: &[u8], compression: GelfCompression) -> io::Result<Vec<u8>> {
+    use std::io::Write;
+
+    match compression {
+        GelfCompression::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(message)?;
+            encoder.finish()
+        },
+        GelfCompression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(message)?;
+            encoder.finish()
+        },
+    }
+}
+
+impl io::Write for &GelfUdpWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for GelfUdpWriter {
+    type Writer = &'a GelfUdpWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::UdpSocket, time::Duration};
+
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::{registry, Layer};
+
+    use super::{GelfUdpWriter, CHUNK_MAGIC};
+    use crate::JsonLayer;
+
+    fn receiver() -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        socket
+    }
+
+    /// Receives datagrams until a whole message is reassembled.
+    fn receive_message(socket: &UdpSocket) -> (Vec<u8>, usize) {
+        let mut buf = vec![0; 65536];
+        let mut chunks: Vec<Option<Vec<u8>>> = Vec::new();
+        let mut datagrams = 0;
+        loop {
+            let len = socket.recv(&mut buf).unwrap();
+            datagrams += 1;
+            let datagram = &buf[..len];
+            let Some(chunk) = datagram.strip_prefix(&CHUNK_MAGIC) else {
+                return (datagram.to_vec(), datagrams);
+            };
+            let sequence = usize::from(chunk[8]);
+            let count = usize::from(chunk[9]);
+            chunks.resize(count, None);
+            chunks[sequence] = Some(chunk[10..].to_vec());
+            if chunks.iter().all(Option::is_some) {
+                return (chunks.into_iter().flatten().flatten().collect(), datagrams);
+            }
+        }
+    }
+
+    fn produce(writer: GelfUdpWriter, producer: impl FnOnce()) {
+        let layer = JsonLayer::gelf(writer);
+        with_default(layer.with_subscriber(registry()), producer);
+    }
+
+    #[test]
+    fn gelf_message() {
+        let socket = receiver();
+        let writer = GelfUdpWriter::new(socket.local_addr().unwrap()).unwrap();
+
+        produce(writer, || {
+            tracing::warn!(id = 7, user = "alice", ok = true, "first line\nsecond line");
+        });
+
+        let (message, datagrams) = receive_message(&socket);
+        assert_eq!(datagrams, 1);
+        let message = serde_json::from_slice::<serde_json::Value>(&message).unwrap();
+        assert_eq!(message["version"], "1.1");
+        assert_eq!(message["host"], crate::process::hostname_or_unknown());
+        assert!(message["timestamp"].as_f64().unwrap() > 1_700_000_000.0);
+        assert_eq!(message["level"], 4);
+        assert_eq!(message["short_message"], "first line");
+        assert_eq!(message["full_message"], "first line\nsecond line");
+        assert_eq!(message["__id"], 7);
+        assert_eq!(message["_user"], "alice");
+        assert_eq!(message["_ok"], "true");
+        assert_eq!(message.as_object().unwrap().len(), 9);
+    }
+
+    #[test]
+    fn chunked_message() {
+        let socket = receiver();
+        let writer = GelfUdpWriter::new(socket.local_addr().unwrap())
+            .unwrap()
+            .with_chunk_size(64);
+
+        let long = "x".repeat(1000);
+        produce(writer, || tracing::info!(long, "chunked"));
+
+        let (message, datagrams) = receive_message(&socket);
+        assert!(datagrams > 1);
+        let message = serde_json::from_slice::<serde_json::Value>(&message).unwrap();
+        assert_eq!(message["short_message"], "chunked");
+        assert_eq!(message["_long"], long);
+        assert!(message.get("full_message").is_none());
+    }
+
+    #[test]
+    fn too_many_chunks() {
+        let socket = receiver();
+        let writer = GelfUdpWriter::new(socket.local_addr().unwrap())
+            .unwrap()
+            .with_chunk_size(13);
+
+        let error = super::GelfUdpWriter::send(&writer, &[b'x'; 129]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "gelf-compression")]
+    #[test]
+    fn compressed_messages() {
+        use std::io::Read;
+
+        use flate2::read::{GzDecoder, ZlibDecoder};
+
+        use super::GelfCompression;
+
+        for compression in [GelfCompression::Zlib, GelfCompression::Gzip] {
+            let socket = receiver();
+            let writer = GelfUdpWriter::new(socket.local_addr().unwrap())
+                .unwrap()
+                .with_chunk_size(64)
+                .with_compression(compression);
+
+            produce(writer, || {
+                tracing::info!(payload = "y".repeat(500), "compressed");
+            });
+
+            let (message, _) = receive_message(&socket);
+            let mut decompressed = String::new();
+            match compression {
+                GelfCompression::Zlib => {
+                    ZlibDecoder::new(&message[..]).read_to_string(&mut decompressed)
+                },
+                GelfCompression::Gzip => {
+                    GzDecoder::new(&message[..]).read_to_string(&mut decompressed)
+                },
+            }
+            .unwrap();
+            let message = serde_json::from_str::<serde_json::Value>(&decompressed).unwrap();
+            assert_eq!(message["short_message"], "compressed");
+        }
+    }
+}
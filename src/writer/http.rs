@@ -0,0 +1,879 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+#[derive(Debug, Clone)]
+enum Format {
+    Ndjson,
+    ElasticsearchBulk {
+        /// The action line preceding each document.
+        action: String,
+    },
+    Loki {
+        /// Names of the fields used as labels with the label names they are sent as.
+        label_fields: Vec<(String, String)>,
+        static_labels: Vec<(String, String)>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    url: String,
+    format: Format,
+    headers: Vec<(String, String)>,
+    max_batch_lines: usize,
+    max_batch_bytes: usize,
+    interval: Duration,
+    max_retries: u32,
+    initial_backoff: Duration,
+    timeout: Duration,
+    max_buffered_lines: usize,
+}
+
+#[derive(Debug)]
+struct Line {
+    /// Time of the write in nanoseconds since the Unix epoch, used by Loki.
+    time: u128,
+    json: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    lines: VecDeque<Line>,
+    bytes: usize,
+    thread: Option<JoinHandle<()>>,
+    started: bool,
+    shutdown: bool,
+    /// Number of lines written so far.
+    written: u64,
+    /// Number of the oldest lines that were sent, failed to be sent or were dropped.
+    completed: u64,
+    /// Number of lines which have to be completed before pending flushes return.
+    flush_until: u64,
+    dropped: usize,
+    failure: Option<String>,
+}
+
+impl Queue {
+    /// Takes the error to report to the next caller, if any lines were dropped.
+    fn take_failure(&mut self) -> Option<io::Error> {
+        let failure = self.failure.take()?;
+        let dropped = std::mem::take(&mut self.dropped);
+        Some(io::Error::other(format!(
+            "{dropped} lines were not exported: {failure}"
+        )))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    condvar: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, queue: MutexGuard<'a, Queue>, timeout: Duration) -> MutexGuard<'a, Queue> {
+        self.condvar
+            .wait_timeout(queue, timeout)
+            .unwrap_or_else(PoisonError::into_inner)
+            .0
+    }
+}
+
+/// A [`MakeWriter`] collecting lines and sending them in batches with HTTP `POST` requests from a
+/// background thread, as newline-delimited JSON, as an [Elasticsearch bulk] request or as a
+/// [Loki push] request.
+///
+/// A batch is sent when it reaches the [maximum number of lines](Self::with_max_batch_lines) or
+/// [bytes](Self::with_max_batch_bytes), or when the [interval](Self::with_interval) elapses.
+/// Requests failing because of the connection, with `429 Too Many Requests` or with a `5xx`
+/// status are retried with exponential backoff. Lines that could not be exported and lines
+/// dropped because the buffer is full are reported as an error of the next write, which
+/// [`JsonLayer`](crate::JsonLayer) prints if
+/// [`log_internal_errors`](crate::JsonLayer::log_internal_errors) is enabled.
+///
+/// Each line is [one write](crate::writer#one-write-per-line).
+///
+/// Remaining lines are sent when the writer is dropped. Since a global subscriber is never
+/// dropped, use [`handle`](Self::handle) to flush or shut down the writer before the program
+/// exits.
+///
+/// Only `http` URLs are supported unless the `http-exporter-tls` feature is enabled.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use tracing_subscriber::prelude::*;
+/// use json_subscriber::{writer::HttpBatchWriter, JsonLayer};
+///
+/// let writer = HttpBatchWriter::loki("http://localhost:3100/loki/api/v1/push", ["level"])
+///     .with_static_label("service", "my-service");
+/// let handle = writer.handle();
+///
+/// let mut layer = JsonLayer::stdout().with_writer(writer);
+/// layer.with_level("level").with_flattened_event();
+/// tracing_subscriber::registry().with(layer).init();
+///
+/// tracing::info!("hello loki");
+/// handle.shutdown();
+/// ```
+///
+/// [Elasticsearch bulk]: https://www.elastic.co/guide/en/elasticsearch/reference/current/docs-bulk.html
+/// [Loki push]: https://grafana.com/docs/loki/latest/reference/loki-http-api/#ingest-logs
+#[cfg_attr(docsrs, doc(cfg(feature = "http-exporter")))]
+#[derive(Debug)]
+pub struct HttpBatchWriter {
+    config: Config,
+    shared: Arc<Shared>,
+}
+
+impl HttpBatchWriter {
+    fn with_format(url: impl Into<String>, format: Format) -> Self {
+        Self {
+            config: Config {
+                url: url.into(),
+                format,
+                headers: Vec::new(),
+                max_batch_lines: 1000,
+                max_batch_bytes: 1024 * 1024,
+                interval: Duration::from_secs(1),
+                max_retries: 3,
+                initial_backoff: Duration::from_millis(200),
+                timeout: Duration::from_secs(10),
+                max_buffered_lines: 10_000,
+            },
+            shared: Arc::default(),
+        }
+    }
+
+    /// Creates a writer sending batches as newline-delimited JSON with the
+    /// `application/x-ndjson` content type.
+    pub fn ndjson(url: impl Into<String>) -> Self {
+        Self::with_format(url, Format::Ndjson)
+    }
+
+    /// Creates a writer sending batches to the Elasticsearch `_bulk` endpoint at `url`, creating
+    /// a document in `index` for each line. This also works with data streams.
+    ///
+    /// Documents rejected by Elasticsearch are reported as errors but not retried.
+    pub fn elasticsearch_bulk(url: impl Into<String>, index: impl AsRef<str>) -> Self {
+        let action = serde_json::json!({ "create": { "_index": index.as_ref() } }).to_string();
+        Self::with_format(url, Format::ElasticsearchBulk { action })
+    }
+
+    /// Creates a writer sending batches to the Loki push endpoint at `url`. Lines are grouped into
+    /// streams by the values of the given top-level fields of each line, which are used as
+    /// labels. Characters not allowed in label names are replaced with `_`.
+    ///
+    /// Lines without any of the fields only have the [static labels](Self::with_static_label).
+    /// Loki rejects streams without any labels.
+    pub fn loki(
+        url: impl Into<String>,
+        label_fields: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        let label_fields = label_fields
+            .into_iter()
+            .map(|field| {
+                let field = field.into();
+                let label = loki_label_name(&field);
+                (field, label)
+            })
+            .collect();
+        Self::with_format(
+            url,
+            Format::Loki {
+                label_fields,
+                static_labels: Vec::new(),
+            },
+        )
+    }
+
+    /// Adds a label with a fixed value to every Loki stream. This does nothing for other
+    /// formats.
+    #[must_use]
+    pub fn with_static_label(mut self, name: impl AsRef<str>, value: impl Into<String>) -> Self {
+        if let Format::Loki { static_labels, .. } = &mut self.config.format {
+            static_labels.push((loki_label_name(name.as_ref()), value.into()));
+        }
+        self
+    }
+
+    /// Adds a header to every request, for example for authorization or a Loki tenant.
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the number of lines which triggers sending a batch. Defaults to 1000.
+    #[must_use]
+    pub fn with_max_batch_lines(mut self, max_batch_lines: usize) -> Self {
+        self.config.max_batch_lines = max_batch_lines.max(1);
+        self
+    }
+
+    /// Sets the size of lines in bytes which triggers sending a batch. Defaults to 1 MiB.
+    #[must_use]
+    pub fn with_max_batch_bytes(mut self, max_batch_bytes: usize) -> Self {
+        self.config.max_batch_bytes = max_batch_bytes.max(1);
+        self
+    }
+
+    /// Sets how often lines are sent if no batch fills up. Defaults to one second.
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.config.interval = interval;
+        self
+    }
+
+    /// Sets how many times a failed request is retried and the delay before the first retry,
+    /// which doubles after each attempt. Defaults to 3 retries starting at 200 milliseconds.
+    #[must_use]
+    pub fn with_retries(mut self, max_retries: u32, initial_backoff: Duration) -> Self {
+        self.config.max_retries = max_retries;
+        self.config.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the timeout of each request. Defaults to 10 seconds.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum number of lines waiting to be sent. When it is exceeded, the oldest lines
+    /// are dropped. Defaults to 10000.
+    #[must_use]
+    pub fn with_max_buffered_lines(mut self, max_buffered_lines: usize) -> Self {
+        self.config.max_buffered_lines = max_buffered_lines.max(1);
+        self
+    }
+
+    /// Returns a handle which can flush and shut down this writer after it has been moved into a
+    /// layer.
+    #[must_use]
+    pub fn handle(&self) -> HttpBatchHandle {
+        HttpBatchHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    fn send(&self, line: &[u8]) -> io::Result<()> {
+        let line = super::strip_newline(line);
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos());
+
+        let mut queue = self.shared.lock();
+        if queue.shutdown {
+            return Err(io::Error::other("the HTTP exporter was shut down"));
+        }
+        if !queue.started {
+            let shared = self.shared.clone();
+            let config = self.config.clone();
+            queue.thread = Some(
+                std::thread::Builder::new()
+                    .name("json-subscriber-http".to_owned())
+                    .spawn(move || run(&shared, &config))?,
+            );
+            queue.started = true;
+        }
+
+        queue.written += 1;
+        queue.bytes += line.len();
+        queue.lines.push_back(Line {
+            time,
+            json: line.to_vec(),
+        });
+        let mut error = None;
+        if queue.lines.len() > self.config.max_buffered_lines {
+            if let Some(dropped) = queue.lines.pop_front() {
+                queue.bytes -= dropped.json.len();
+                queue.completed += 1;
+            }
+            error = Some(io::Error::other(format!(
+                "the buffer of {} lines is full, dropped the oldest line",
+                self.config.max_buffered_lines,
+            )));
+        }
+        if queue.lines.len() >= self.config.max_batch_lines
+            || queue.bytes >= self.config.max_batch_bytes
+        {
+            self.shared.condvar.notify_all();
+        }
+
+        match queue.take_failure().or(error) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for HttpBatchWriter {
+    fn drop(&mut self) {
+        self.handle().shutdown();
+    }
+}
+
+impl io::Write for &HttpBatchWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.handle().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for HttpBatchWriter {
+    type Writer = &'a HttpBatchWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self
+    }
+}
+
+/// A handle to an [`HttpBatchWriter`] used to flush it or shut it down from elsewhere.
+#[cfg_attr(docsrs, doc(cfg(feature = "http-exporter")))]
+#[derive(Debug, Clone)]
+pub struct HttpBatchHandle {
+    shared: Arc<Shared>,
+}
+
+impl HttpBatchHandle {
+    /// Sends all lines written so far and waits until they are exported, including retries.
+    /// Lines written while waiting are not waited for.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any lines could not be exported since the last reported error.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut queue = self.shared.lock();
+        if queue.started && !queue.shutdown {
+            let target = queue.written;
+            queue.flush_until = queue.flush_until.max(target);
+            self.shared.condvar.notify_all();
+            while queue.completed < target
+                && !queue.thread.as_ref().is_some_and(JoinHandle::is_finished)
+            {
+                queue = self.shared.wait(queue, Duration::from_secs(1));
+            }
+        }
+        match queue.take_failure() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Sends all remaining lines and stops the background thread. Lines written afterwards are
+    /// dropped with an error.
+    pub fn shutdown(&self) {
+        let thread = {
+            let mut queue = self.shared.lock();
+            queue.shutdown = true;
+            self.shared.condvar.notify_all();
+            queue.thread.take()
+        };
+        if let Some(thread) = thread {
+            _ = thread.join();
+        }
+    }
+}
+
+/// The loop of the background thread sending batches.
+fn run(shared: &Shared, config: &Config) {
+    let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+    let mut deadline = Instant::now() + config.interval;
+
+    let mut queue = shared.lock();
+    loop {
+        let now = Instant::now();
+        let full =
+            queue.lines.len() >= config.max_batch_lines || queue.bytes >= config.max_batch_bytes;
+        let flush = queue.flush_until > queue.completed || queue.shutdown;
+        if !full && !flush && now < deadline {
+            queue = shared.wait(queue, deadline - now);
+            continue;
+        }
+        if now >= deadline {
+            deadline = now + config.interval;
+        }
+
+        let mut batch_bytes = 0;
+        let mut batch_len = 0;
+        for line in &queue.lines {
+            if batch_len >= config.max_batch_lines
+                || (batch_len > 0 && batch_bytes + line.json.len() > config.max_batch_bytes)
+            {
+                break;
+            }
+            batch_bytes += line.json.len();
+            batch_len += 1;
+        }
+        let batch = queue.lines.drain(..batch_len).collect::<Vec<_>>();
+        queue.bytes -= batch_bytes;
+
+        if !batch.is_empty() {
+            drop(queue);
+            let result = send_batch(&agent, config, &batch);
+            queue = shared.lock();
+            if let Err((dropped, failure)) = result {
+                queue.dropped += dropped;
+                queue.failure = Some(failure);
+            }
+            queue.completed += batch.len() as u64;
+            shared.condvar.notify_all();
+        }
+
+        if queue.shutdown && queue.lines.is_empty() {
+            return;
+        }
+    }
+}
+
+/// Sends the batch, retrying transient failures. On failure, returns the number of lines which
+/// were not exported and the reason.
+fn send_batch(agent: &ureq::Agent, config: &Config, batch: &[Line]) -> Result<(), (usize, String)> {
+    let (body, content_type) = match &config.format {
+        Format::Ndjson => (ndjson_body(batch, None), "application/x-ndjson"),
+        Format::ElasticsearchBulk { action } => {
+            (ndjson_body(batch, Some(action)), "application/x-ndjson")
+        },
+        Format::Loki {
+            label_fields,
+            static_labels,
+        } => {
+            (
+                loki_body(batch, label_fields, static_labels),
+                "application/json",
+            )
+        },
+    };
+
+    let mut backoff = config.initial_backoff;
+    let mut attempt = 0;
+    loop {
+        let mut request = agent.post(&config.url).set("Content-Type", content_type);
+        for (name, value) in &config.headers {
+            request = request.set(name, value);
+        }
+
+        let error = match request.send_bytes(&body) {
+            Ok(response) => return check_response(&config.format, response, batch.len()),
+            Err(ureq::Error::Status(status, _)) if status != 429 && status < 500 => {
+                return Err((
+                    batch.len(),
+                    format!("server responded with status {status}"),
+                ));
+            },
+            Err(error) => error.to_string(),
+        };
+        if attempt >= config.max_retries {
+            return Err((batch.len(), error));
+        }
+        attempt += 1;
+        std::thread::sleep(backoff);
+        backoff *= 2;
+    }
+}
+
+/// Checks the response of Elasticsearch for documents which were rejected. Only those are counted
+/// as not exported since the rest of the batch was indexed.
+fn check_response(
+    format: &Format,
+    response: ureq::Response,
+    batch_len: usize,
+) -> Result<(), (usize, String)> {
+    if !matches!(format, Format::ElasticsearchBulk { .. }) {
+        return Ok(());
+    }
+
+    let response = serde_json::from_reader::<_, serde_json::Value>(response.into_reader())
+        .map_err(|error| {
+            (
+                batch_len,
+                format!("unable to read Elasticsearch response: {error}"),
+            )
+        })?;
+    if response["errors"] != true {
+        return Ok(());
+    }
+    let items = response["items"].as_array().map_or(&[][..], Vec::as_slice);
+    let failed = items
+        .iter()
+        .filter_map(|item| item.as_object()?.values().next())
+        .filter(|result| {
+            result["status"]
+                .as_u64()
+                .is_some_and(|status| status >= 300)
+        })
+        .collect::<Vec<_>>();
+    if failed.is_empty() {
+        return Ok(());
+    }
+    Err((
+        failed.len(),
+        format!(
+            "Elasticsearch rejected {} documents, the first because of {}",
+            failed.len(),
+            failed
+                .first()
+                .map_or_else(String::new, |result| result["error"].to_string()),
+        ),
+    ))
+}
+
+fn ndjson_body(batch: &[Line], action: Option<&str>) -> Vec<u8> {
+    let mut body = Vec::with_capacity(
+        batch
+            .iter()
+            .map(|line| line.json.len() + action.map_or(0, str::len) + 2)
+            .sum(),
+    );
+    for line in batch {
+        if let Some(action) = action {
+            body.extend_from_slice(action.as_bytes());
+            body.push(b'\n');
+        }
+        body.extend_from_slice(&line.json);
+        body.push(b'\n');
+    }
+    body
+}
+
+fn loki_body(
+    batch: &[Line],
+    label_fields: &[(String, String)],
+    static_labels: &[(String, String)],
+) -> Vec<u8> {
+    let mut streams = BTreeMap::<BTreeMap<&str, String>, Vec<[String; 2]>>::new();
+    for line in batch {
+        let mut labels = static_labels
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect::<BTreeMap<_, _>>();
+        if !label_fields.is_empty() {
+            if let Ok(object) =
+                serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&line.json)
+            {
+                for (field, label) in label_fields {
+                    match object.get(field) {
+                        Some(serde_json::Value::String(value)) => {
+                            labels.insert(label, value.clone());
+                        },
+                        Some(serde_json::Value::Null) | None => {},
+                        Some(value) => {
+                            labels.insert(label, value.to_string());
+                        },
+                    }
+                }
+            }
+        }
+        streams.entry(labels).or_default().push([
+            line.time.to_string(),
+            String::from_utf8_lossy(&line.json).into_owned(),
+        ]);
+    }
+
+    let streams = streams
+        .into_iter()
+        .map(|(labels, values)| serde_json::json!({ "stream": labels, "values": values }))
+        .collect::<Vec<_>>();
+    serde_json::to_vec(&serde_json::json!({ "streams": streams })).unwrap_or_default()
+}
+
+/// Converts a field name into a valid Loki label name.
+fn loki_label_name(field: &str) -> String {
+    let mut label = field
+        .chars()
+        .map(|char| {
+            if char.is_ascii_alphanumeric() || char == '_' {
+                char
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if label.is_empty() || label.starts_with(|char: char| char.is_ascii_digit()) {
+        label.insert(0, '_');
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        time::Duration,
+    };
+
+    use serde_json::{json, Value};
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::{registry, Layer};
+
+    use super::HttpBatchWriter;
+    use crate::JsonLayer;
+
+    struct Request {
+        path: String,
+        headers: Vec<(String, String)>,
+        body: String,
+    }
+
+    impl Request {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// Starts a server answering requests with the given statuses and bodies, repeating the last
+    /// one, and returns its URL and the received requests.
+    fn server(responses: Vec<(u16, &'static str)>) -> (String, mpsc::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap().to_owned();
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(": ") else {
+                        break;
+                    };
+                    headers.push((name.to_owned(), value.to_owned()));
+                }
+                let len = headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .map_or(0, |(_, value)| value.parse().unwrap());
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+
+                let (status, response) = responses[index.min(responses.len() - 1)];
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Status\r\nContent-Type: \
+                     application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                    response.len(),
+                )
+                .unwrap();
+
+                let request = Request {
+                    path,
+                    headers,
+                    body: String::from_utf8(body).unwrap(),
+                };
+                if sender.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+        (url, receiver)
+    }
+
+    fn produce(writer: HttpBatchWriter, producer: impl FnOnce()) {
+        let mut layer = JsonLayer::stdout().with_writer(writer);
+        layer.with_level("level").with_flattened_event();
+        with_default(layer.with_subscriber(registry()), producer);
+    }
+
+    fn receive(receiver: &mpsc::Receiver<Request>) -> Request {
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap()
+    }
+
+    #[test]
+    fn ndjson_batches() {
+        let (url, requests) = server(vec![(200, "")]);
+        let writer = HttpBatchWriter::ndjson(format!("{url}/ingest"))
+            .with_max_batch_lines(2)
+            .with_interval(Duration::from_secs(60))
+            .with_header("Authorization", "Bearer token");
+
+        produce(writer, || {
+            tracing::info!("first");
+            tracing::info!("second");
+            tracing::info!("third");
+        });
+
+        let request = receive(&requests);
+        assert_eq!(request.path, "/ingest");
+        assert_eq!(request.header("content-type"), Some("application/x-ndjson"));
+        assert_eq!(request.header("authorization"), Some("Bearer token"));
+        assert_eq!(
+            request.body,
+            concat!(
+                r#"{"level":"INFO","message":"first"}"#,
+                "\n",
+                r#"{"level":"INFO","message":"second"}"#,
+                "\n",
+            )
+        );
+        // The rest is sent when the writer is dropped.
+        let request = receive(&requests);
+        assert_eq!(request.body, "{\"level\":\"INFO\",\"message\":\"third\"}\n");
+    }
+
+    #[test]
+    fn elasticsearch_bulk() {
+        let (url, requests) = server(vec![
+            (200, r#"{"errors":false,"items":[]}"#),
+            (
+                200,
+                r#"{"errors":true,"items":[{"create":{"status":201}},{"create":{"status":400,"error":{"type":"mapper_parsing_exception"}}}]}"#,
+            ),
+        ]);
+        let writer = HttpBatchWriter::elasticsearch_bulk(format!("{url}/_bulk"), "logs");
+        let handle = writer.handle();
+
+        produce(writer, || {
+            tracing::warn!(answer = 42, "bulk");
+            handle.flush().unwrap();
+            tracing::warn!("accepted");
+            tracing::warn!("rejected");
+            let error = handle.flush().unwrap_err().to_string();
+            // Only the rejected document is counted as not exported.
+            assert!(error.starts_with("1 lines were not exported"), "{error}");
+            assert!(error.contains("mapper_parsing_exception"));
+        });
+
+        let request = receive(&requests);
+        assert_eq!(request.path, "/_bulk");
+        let lines = request.body.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<Value>(lines[0]).unwrap(),
+            json!({ "create": { "_index": "logs" } })
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(lines[1]).unwrap(),
+            json!({ "level": "WARN", "message": "bulk", "answer": 42 })
+        );
+    }
+
+    #[test]
+    fn elasticsearch_errors_without_failed_items() {
+        let (url, requests) = server(vec![(
+            200,
+            r#"{"errors":true,"items":[{"create":{"status":201}}]}"#,
+        )]);
+        let writer = HttpBatchWriter::elasticsearch_bulk(url, "logs");
+        let handle = writer.handle();
+
+        produce(writer, || {
+            tracing::warn!("accepted");
+            handle.flush().unwrap();
+        });
+        receive(&requests);
+    }
+
+    #[test]
+    fn loki_streams() {
+        let (url, requests) = server(vec![(204, "")]);
+        let writer = HttpBatchWriter::loki(format!("{url}/loki/api/v1/push"), ["level", "user.id"])
+            .with_static_label("service", "test");
+        let handle = writer.handle();
+
+        produce(writer, || {
+            tracing::info!("one");
+            tracing::warn!(user.id = 7, "two");
+            tracing::info!("three");
+            handle.flush().unwrap();
+        });
+
+        let request = receive(&requests);
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        let body = serde_json::from_str::<Value>(&request.body).unwrap();
+        let streams = body["streams"].as_array().unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(
+            streams[0]["stream"],
+            json!({ "level": "INFO", "service": "test" })
+        );
+        let values = streams[0]["values"].as_array().unwrap();
+        assert_eq!(values.len(), 2);
+        assert!(values[0][0].as_str().unwrap().parse::<u128>().is_ok());
+        assert_eq!(values[0][1], r#"{"level":"INFO","message":"one"}"#);
+        assert_eq!(values[1][1], r#"{"level":"INFO","message":"three"}"#);
+        assert_eq!(
+            streams[1]["stream"],
+            json!({ "level": "WARN", "service": "test", "user_id": "7" })
+        );
+    }
+
+    #[test]
+    fn retries() {
+        let (url, requests) = server(vec![(503, ""), (429, ""), (200, "")]);
+        let writer = HttpBatchWriter::ndjson(url).with_retries(2, Duration::from_millis(1));
+        let handle = writer.handle();
+
+        produce(writer, || {
+            tracing::info!("retried");
+            handle.flush().unwrap();
+        });
+
+        let bodies = (0..3).map(|_| receive(&requests).body).collect::<Vec<_>>();
+        assert_eq!(bodies[0], bodies[2]);
+        assert!(bodies[0].contains("retried"));
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        let (url, requests) = server(vec![(400, "")]);
+        let writer = HttpBatchWriter::ndjson(url).with_retries(5, Duration::from_millis(1));
+        let handle = writer.handle();
+
+        produce(writer, || {
+            tracing::info!("rejected");
+            let error = handle.flush().unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "1 lines were not exported: server responded with status 400"
+            );
+            // Client errors are not retried.
+            receive(&requests);
+            assert!(requests.try_recv().is_err());
+        });
+    }
+
+    #[test]
+    fn shutdown_flush() {
+        let (url, requests) = server(vec![(200, "")]);
+        let writer = HttpBatchWriter::ndjson(url).with_interval(Duration::from_secs(60));
+        let handle = writer.handle();
+
+        let mut layer = JsonLayer::stdout().with_writer(writer);
+        layer.with_flattened_event();
+        let subscriber = layer.with_subscriber(registry());
+        with_default(subscriber, || {
+            tracing::info!("before");
+            handle.shutdown();
+            assert!(receive(&requests).body.contains("before"));
+            tracing::info!("after");
+        });
+        assert!(requests.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+use std::{io, os::unix::net::UnixDatagram, path::Path};
+
+use tracing_core::Metadata;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::{level_format::syslog_severity, process};
+
+/// A [`MakeWriter`] sending each written line to [journald] through its [native protocol].
+///
+/// Each journal entry is [one write](crate::writer#one-write-per-line). The entry contains
+/// `PRIORITY` mapped from the level as in syslog, `CODE_FILE` and `CODE_LINE` of the event,
+/// `SYSLOG_IDENTIFIER` and the line as `MESSAGE`. With the `tracing-log` feature, `CODE_FILE` and
+/// `CODE_LINE` of events from `log` records are those of the record. With
+/// [`with_journal_fields`](Self::with_journal_fields), the top-level keys of the line become
+/// journal fields instead.
+///
+/// Entries larger than the maximum datagram size cannot be sent because that requires passing
+/// a file descriptor to journald, which is not supported.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use tracing_subscriber::prelude::*;
+/// # fn main() -> std::io::Result<()> {
+/// use json_subscriber::{writer::JournaldWriter, JsonLayer};
+///
+/// let mut layer = JsonLayer::stdout().with_writer(JournaldWriter::new()?);
+/// layer.with_flattened_event();
+/// # tracing_subscriber::registry().with(layer);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [journald]: https://www.freedesktop.org/software/systemd/man/latest/systemd-journald.service.html
+/// [native protocol]: https://systemd.io/JOURNAL_NATIVE_PROTOCOL/
+#[derive(Debug)]
+pub struct JournaldWriter {
+    socket: UnixDatagram,
+    syslog_identifier: Option<String>,
+    journal_fields: bool,
+}
+
+impl JournaldWriter {
+    /// The path of the socket where journald listens for native messages.
+    pub const DEFAULT_SOCKET_PATH: &'static str = "/run/systemd/journal/socket";
+
+    /// Creates a writer sending entries to the journald socket at
+    /// [`DEFAULT_SOCKET_PATH`](Self::DEFAULT_SOCKET_PATH).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be connected, usually because journald is not
+    /// running.
+    pub fn new() -> io::Result<Self> {
+        Self::with_socket_path(Self::DEFAULT_SOCKET_PATH)
+    }
+
+    /// Creates a writer sending entries to a socket at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be connected.
+    pub fn with_socket_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self {
+            socket,
+            syslog_identifier: process::executable_name(),
+            journal_fields: false,
+        })
+    }
+
+    /// Sets the `SYSLOG_IDENTIFIER` of entries. Defaults to the name of the executable.
+    #[must_use]
+    pub fn with_syslog_identifier(self, syslog_identifier: impl Into<String>) -> Self {
+        Self {
+            syslog_identifier: Some(syslog_identifier.into()),
+            ..self
+        }
+    }
+
+    /// Sets whether the top-level keys of each JSON line are sent as separate journal fields
+    /// instead of sending the whole line as `MESSAGE`.
+    ///
+    /// Keys are uppercased and characters other than ASCII letters, digits and `_` are replaced
+    /// with `_`. Leading underscores and digits are removed since journald does not accept them
+    /// from clients. String values are sent as they are and other values as JSON. The `message`
+    /// key becomes `MESSAGE`. Lines without a `message` are still sent whole as `MESSAGE`.
+    #[must_use]
+    pub fn with_journal_fields(self, journal_fields: bool) -> Self {
+        Self {
+            journal_fields,
+            ..self
+        }
+    }
+
+    /// Encodes the fields describing the event's metadata.
+    fn metadata_fields(metadata: &Metadata<'_>) -> Vec<u8> {
+        let mut fields = Vec::with_capacity(128);
+        push_field(
+            &mut fields,
+            "PRIORITY",
+            syslog_severity(*metadata.level()).to_string().as_bytes(),
+        );
+        if let Some(file) = metadata.file() {
+            push_field(&mut fields, "CODE_FILE", file.as_bytes());
+        }
+        if let Some(line) = metadata.line() {
+            push_field(&mut fields, "CODE_LINE", line.to_string().as_bytes());
+        }
+        fields
+    }
+
+    fn send(&self, metadata_fields: &[u8], line: &[u8]) -> io::Result<()> {
+        let line = super::strip_newline(line);
+
+        let mut entry = Vec::with_capacity(metadata_fields.len() + line.len() + 64);
+        entry.extend_from_slice(metadata_fields);
+        if let Some(syslog_identifier) = &self.syslog_identifier {
+            push_field(
+                &mut entry,
+                "SYSLOG_IDENTIFIER",
+                syslog_identifier.as_bytes(),
+            );
+        }
+
+        let object = if self.journal_fields {
+            serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(line).ok()
+        } else {
+            None
+        };
+        match object {
+            Some(object) if object.contains_key("message") => {
+                for (key, value) in &object {
+                    let Some(name) = journal_field_name(key) else {
+                        continue;
+                    };
+                    match value {
+                        serde_json::Value::String(value) => {
+                            push_field(&mut entry, &name, value.as_bytes());
+                        },
+                        value => push_field(&mut entry, &name, value.to_string().as_bytes()),
+                    }
+                }
+            },
+            _ => push_field(&mut entry, "MESSAGE", line),
+        }
+
+        self.socket.send(&entry)?;
+        Ok(())
+    }
+}
+
+/// Appends a field in the native protocol's format, using the binary encoding for values
+/// containing newlines.
+fn push_field(entry: &mut Vec<u8>, name: &str, value: &[u8]) {
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value);
+    entry.push(b'\n');
+}
+
+/// Converts a JSON key into a valid journal field name.
+fn journal_field_name(key: &str) -> Option<String> {
+    let name = key
+        .trim_start_matches(|char: char| char == '_' || char.is_ascii_digit())
+        .chars()
+        .map(|char| {
+            if char.is_ascii_alphanumeric() {
+                char.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect::<String>();
+    (!name.is_empty()).then_some(name)
+}
+
+/// The [`io::Write`] implementation returned by [`JournaldWriter`]. It adds the metadata of the
+/// event being written to the entry.
+#[derive(Debug)]
+pub struct JournaldEntry<'a> {
+    writer: &'a JournaldWriter,
+    metadata_fields: Vec<u8>,
+}
+
+impl io::Write for JournaldEntry<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.send(&self.metadata_fields, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for JournaldWriter {
+    type Writer = JournaldEntry<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        JournaldEntry {
+            writer: self,
+            metadata_fields: Vec::new(),
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        JournaldEntry {
+            writer: self,
+            metadata_fields: JournaldWriter::metadata_fields(meta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixDatagram, time::Duration};
+
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::{registry, Layer};
+
+    use super::JournaldWriter;
+    use crate::{tests::TempSocketPath, JsonLayer};
+
+    struct Journal {
+        socket: UnixDatagram,
+        path: TempSocketPath,
+    }
+
+    impl Journal {
+        fn new() -> Self {
+            let path = TempSocketPath::new();
+            let socket = UnixDatagram::bind(&path).unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            Self { socket, path }
+        }
+
+        /// Receives an entry and decodes both the simple and the binary field encoding.
+        fn receive(&self) -> Vec<(String, String)> {
+            let mut buf = vec![0; 65536];
+            let len = self.socket.recv(&mut buf).unwrap();
+            let mut rest = &buf[..len];
+            let mut fields = Vec::new();
+            while !rest.is_empty() {
+                let end = rest.iter().position(|byte| *byte == b'\n').unwrap();
+                let line = std::str::from_utf8(&rest[..end]).unwrap();
+                if let Some((name, value)) = line.split_once('=') {
+                    fields.push((name.to_owned(), value.to_owned()));
+                    rest = &rest[end + 1..];
+                } else {
+                    let size = u64::from_le_bytes(rest[end + 1..end + 9].try_into().unwrap());
+                    let size = usize::try_from(size).unwrap();
+                    let value = &rest[end + 9..end + 9 + size];
+                    fields.push((
+                        line.to_owned(),
+                        std::str::from_utf8(value).unwrap().to_owned(),
+                    ));
+                    assert_eq!(rest[end + 9 + size], b'\n');
+                    rest = &rest[end + 10 + size..];
+                }
+            }
+            fields
+        }
+    }
+
+    fn field<'a>(fields: &'a [(String, String)], name: &str) -> &'a str {
+        &fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .unwrap_or_else(|| panic!("missing field {name}"))
+            .1
+    }
+
+    #[test]
+    fn message_entry() {
+        let journal = Journal::new();
+        let writer = JournaldWriter::with_socket_path(&journal.path)
+            .unwrap()
+            .with_syslog_identifier("test");
+        let mut layer = JsonLayer::stdout().with_writer(writer);
+        layer.with_flattened_event();
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::warn!(answer = 42, "hello journal");
+        });
+
+        let fields = journal.receive();
+        assert_eq!(field(&fields, "PRIORITY"), "4");
+        assert!(field(&fields, "CODE_FILE").ends_with("journald.rs"));
+        assert!(field(&fields, "CODE_LINE").parse::<u32>().is_ok());
+        assert_eq!(field(&fields, "SYSLOG_IDENTIFIER"), "test");
+        let message = serde_json::from_str::<serde_json::Value>(field(&fields, "MESSAGE")).unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({ "message": "hello journal", "answer": 42 })
+        );
+        assert_eq!(fields.len(), 5);
+    }
+
+    #[cfg(feature = "tracing-log")]
+    #[test]
+    fn log_record_entry() {
+        use tracing_log::log;
+
+        let journal = Journal::new();
+        let writer = JournaldWriter::with_socket_path(&journal.path).unwrap();
+        let layer = JsonLayer::stdout().with_writer(writer);
+
+        with_default(layer.with_subscriber(registry()), || {
+            let record = log::Record::builder()
+                .args(format_args!("from log"))
+                .level(log::Level::Error)
+                .target("my_crate::module")
+                .file_static(Some("src/module.rs"))
+                .line(Some(42))
+                .build();
+            tracing_log::format_trace(&record).unwrap();
+        });
+
+        let fields = journal.receive();
+        assert_eq!(field(&fields, "PRIORITY"), "3");
+        assert_eq!(field(&fields, "CODE_FILE"), "src/module.rs");
+        assert_eq!(field(&fields, "CODE_LINE"), "42");
+    }
+
+    #[test]
+    fn journal_fields() {
+        let journal = Journal::new();
+        let writer = JournaldWriter::with_socket_path(&journal.path)
+            .unwrap()
+            .with_journal_fields(true);
+        let mut layer = JsonLayer::stdout().with_writer(writer);
+        layer.with_flattened_event().with_current_span("span");
+
+        with_default(layer.with_subscriber(registry()), || {
+            let _span = tracing::info_span!("request", id = 7).entered();
+            tracing::error!(
+                user = "alice",
+                "http.status" = 500,
+                _private = true,
+                "first line\nsecond line"
+            );
+        });
+
+        let fields = journal.receive();
+        assert_eq!(field(&fields, "PRIORITY"), "3");
+        assert_eq!(field(&fields, "MESSAGE"), "first line\nsecond line");
+        assert_eq!(field(&fields, "USER"), "alice");
+        assert_eq!(field(&fields, "HTTP_STATUS"), "500");
+        assert_eq!(field(&fields, "PRIVATE"), "true");
+        let span = serde_json::from_str::<serde_json::Value>(field(&fields, "SPAN")).unwrap();
+        assert_eq!(span, serde_json::json!({ "name": "request", "id": 7 }));
+    }
+
+    #[test]
+    fn journal_fields_without_message() {
+        let journal = Journal::new();
+        let writer = JournaldWriter::with_socket_path(&journal.path)
+            .unwrap()
+            .with_journal_fields(true);
+        let mut layer = JsonLayer::stdout().with_writer(writer);
+        layer.with_event("fields");
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::debug!(answer = 42);
+        });
+
+        let fields = journal.receive();
+        assert_eq!(field(&fields, "PRIORITY"), "7");
+        assert_eq!(field(&fields, "MESSAGE"), r#"{"fields":{"answer":42}}"#);
+    }
+
+    #[test]
+    fn field_names() {
+        assert_eq!(
+            super::journal_field_name("http.status").as_deref(),
+            Some("HTTP_STATUS")
+        );
+        assert_eq!(super::journal_field_name("__1abc").as_deref(), Some("ABC"));
+        assert_eq!(super::journal_field_name("_").as_deref(), None);
+        assert_eq!(
+            super::journal_field_name(&"x".repeat(100)).unwrap().len(),
+            64
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! [`MakeWriter`](tracing_subscriber::fmt::MakeWriter) implementations sending logs to
+//! destinations other than files and standard streams.
+//!
+//! # One write per line
+//!
+//! [`JsonLayer`](crate::JsonLayer) writes each event with a single call to
+//! [`write`](std::io::Write::write), so these writers treat every call as one whole line and send
+//! it as one message, entry or datagram. Where the destination delimits messages itself, the
+//! trailing newline of the line is removed.
+
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
+
+mod gelf;
+#[cfg(feature = "http-exporter")]
+mod http;
+#[cfg(unix)]
+mod journald;
+mod router;
+mod stream;
+mod syslog;
+
+#[cfg(feature = "gelf-compression")]
+pub use gelf::GelfCompression;
+pub use gelf::GelfUdpWriter;
+#[cfg(feature = "http-exporter")]
+pub use http::{HttpBatchHandle, HttpBatchWriter};
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+pub use journald::{JournaldEntry, JournaldWriter};
+pub use router::{Router, RouterWriter};
+pub use stream::StreamWriter;
+pub use syslog::{SyslogEntry, SyslogFacility, SyslogWriter};
+
+/// Binds a UDP socket to an ephemeral local port and connects it to the first of the resolved
+/// addresses which works.
+fn connect_udp(address: impl ToSocketAddrs) -> io::Result<UdpSocket> {
+    let mut last_error = None;
+    for address in address.to_socket_addrs()? {
+        let local: SocketAddr = if address.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        match UdpSocket::bind(local).and_then(|socket| {
+            socket.connect(address)?;
+            Ok(socket)
+        }) {
+            Ok(socket) => return Ok(socket),
+            Err(error) => last_error = Some(error),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing")
+    }))
+}
+
+/// Removes the trailing newline written by [`JsonLayer`](crate::JsonLayer) after each line.
+pub(crate) fn strip_newline(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\n").unwrap_or(line)
+}
@@ -0,0 +1,273 @@
+use std::{fmt, io};
+
+use tracing_core::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// A [`MakeWriter`] erased so that routes can hold writers of different types.
+trait DynMakeWriter: Send + Sync {
+    fn make_writer(&self) -> Box<dyn io::Write + '_>;
+
+    fn make_writer_for(&self, meta: &Metadata<'_>) -> Box<dyn io::Write + '_>;
+}
+
+impl<M> DynMakeWriter for M
+where
+    M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    fn make_writer(&self) -> Box<dyn io::Write + '_> {
+        Box::new(MakeWriter::make_writer(self))
+    }
+
+    fn make_writer_for(&self, meta: &Metadata<'_>) -> Box<dyn io::Write + '_> {
+        Box::new(MakeWriter::make_writer_for(self, meta))
+    }
+}
+
+type Predicate = Box<dyn Fn(&Metadata<'_>) -> bool + Send + Sync>;
+
+/// A [`MakeWriter`] sending each event to every writer whose route matches the event's metadata,
+/// and to a fallback writer if no route matches.
+///
+/// The event is formatted only once and the same line is written to all selected writers. Use it
+/// with [`JsonLayer::with_writer`](crate::JsonLayer::with_writer) or
+/// [`SubscriberBuilder::with_writer`](crate::fmt::SubscriberBuilder::with_writer).
+///
+/// Writers created without metadata through [`MakeWriter::make_writer`] go to the fallback, or
+/// to all routes if there is no fallback.
+///
+/// # Examples
+///
+/// Send warnings and errors to stderr and a file, and everything else to stdout:
+///
+/// ```rust,no_run
+/// use std::{fs::File, sync::Mutex};
+///
+/// use json_subscriber::writer::Router;
+/// use tracing::Level;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let router = Router::new()
+///     .route_level(Level::WARN, std::io::stderr)
+///     .route_level(Level::WARN, Mutex::new(File::create("errors.log")?))
+///     .fallback(std::io::stdout);
+/// json_subscriber::fmt().with_writer(router).init();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Router {
+    routes: Vec<(Predicate, Box<dyn DynMakeWriter>)>,
+    fallback: Option<Box<dyn DynMakeWriter>>,
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("routes", &self.routes.len())
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Router {
+    /// Creates a router without any routes, which discards all events.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            routes: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Sends events whose metadata matches `predicate` to `make_writer`.
+    #[must_use]
+    pub fn route<P, M>(mut self, predicate: P, make_writer: M) -> Self
+    where
+        P: Fn(&Metadata<'_>) -> bool + Send + Sync + 'static,
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        self.routes
+            .push((Box::new(predicate), Box::new(make_writer)));
+        self
+    }
+
+    /// Sends events at `level` or more severe to `make_writer`. For example [`Level::WARN`]
+    /// matches warnings and errors.
+    #[must_use]
+    pub fn route_level<M>(self, level: Level, make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        self.route(move |meta| *meta.level() <= level, make_writer)
+    }
+
+    /// Sends events whose target is `target` or one of its submodules to `make_writer`.
+    #[must_use]
+    pub fn route_target<M>(self, target: impl Into<String>, make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        let target = target.into();
+        self.route(
+            move |meta| {
+                meta.target()
+                    .strip_prefix(target.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            },
+            make_writer,
+        )
+    }
+
+    /// Sends events which do not match any route to `make_writer`.
+    #[must_use]
+    pub fn fallback<M>(self, make_writer: M) -> Self
+    where
+        M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        Self {
+            fallback: Some(Box::new(make_writer)),
+            ..self
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for Router {
+    type Writer = RouterWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        let writers = match &self.fallback {
+            Some(fallback) => vec![fallback.make_writer()],
+            None => {
+                self.routes
+                    .iter()
+                    .map(|(_, make_writer)| make_writer.make_writer())
+                    .collect()
+            },
+        };
+        RouterWriter { writers }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        let mut writers = self
+            .routes
+            .iter()
+            .filter(|(predicate, _)| predicate(meta))
+            .map(|(_, make_writer)| make_writer.make_writer_for(meta))
+            .collect::<Vec<_>>();
+        if writers.is_empty() {
+            if let Some(fallback) = &self.fallback {
+                writers.push(fallback.make_writer_for(meta));
+            }
+        }
+        RouterWriter { writers }
+    }
+}
+
+/// The [`io::Write`] implementation returned by [`Router`]. It writes everything to all writers
+/// selected for the event.
+pub struct RouterWriter<'a> {
+    writers: Vec<Box<dyn io::Write + 'a>>,
+}
+
+impl fmt::Debug for RouterWriter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RouterWriter")
+            .field("writers", &self.writers.len())
+            .finish()
+    }
+}
+
+impl io::Write for RouterWriter<'_> {
+    /// Writes the whole buffer to every writer, even if some of them fail, and returns the first
+    /// error.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut result = Ok(buf.len());
+        for writer in &mut self.writers {
+            if let Err(error) = writer.write_all(buf) {
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        }
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        for writer in &mut self.writers {
+            if let Err(error) = writer.flush() {
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::{subscriber::with_default, Level};
+
+    use super::Router;
+    use crate::tests::MockMakeWriter;
+
+    fn lines(make_writer: &MockMakeWriter) -> Vec<String> {
+        std::str::from_utf8(&make_writer.buf())
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["fields"]["message"]
+                    .as_str()
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn routes_and_fallback() {
+        let severe = MockMakeWriter::default();
+        let audit = MockMakeWriter::default();
+        let rest = MockMakeWriter::default();
+        let router = Router::new()
+            .route_level(Level::WARN, severe.clone())
+            .route_target("audit", audit.clone())
+            .fallback(rest.clone());
+
+        let subscriber = crate::fmt().with_writer(router).finish();
+        with_default(subscriber, || {
+            tracing::info!("info");
+            tracing::warn!("warn");
+            tracing::info!(target: "audit::login", "audit info");
+            tracing::info!(target: "auditing", "not audit");
+            tracing::error!(target: "audit", "audit error");
+        });
+
+        assert_eq!(lines(&severe), ["warn", "audit error"]);
+        assert_eq!(lines(&audit), ["audit info", "audit error"]);
+        assert_eq!(lines(&rest), ["info", "not audit"]);
+    }
+
+    #[test]
+    fn without_fallback() {
+        let all = MockMakeWriter::default();
+        let router = Router::new().route(|meta| meta.name().contains("kept"), all.clone());
+
+        let mut layer = crate::JsonLayer::stdout().with_writer(router);
+        layer.with_event("fields");
+        let subscriber =
+            tracing_subscriber::Layer::with_subscriber(layer, tracing_subscriber::registry());
+        with_default(subscriber, || {
+            tracing::info!(name: "kept", "first");
+            tracing::info!(name: "dropped", "second");
+        });
+
+        assert_eq!(lines(&all), ["first"]);
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::PathBuf};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+#[derive(Debug)]
+enum Address {
+    Tcp(Vec<SocketAddr>),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+#[derive(Debug)]
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn write_all(&mut self, line: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.write_all(line),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write_all(line),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    stream: Option<Stream>,
+    buffer: VecDeque<Vec<u8>>,
+    backoff: Duration,
+    next_attempt: Instant,
+}
+
+/// A [`MakeWriter`] streaming newline-delimited lines over a TCP or Unix stream socket, for
+/// example to a log shipper such as Vector or Fluent Bit running as a sidecar.
+///
+/// The connection is established on the first write. If connecting or writing fails, the writer
+/// reconnects with exponential backoff. Lines written while disconnected are buffered up to a
+/// bounded number and sent in order once the connection is restored. When the buffer is full, the
+/// oldest line is dropped and the write returns an error, which
+/// [`JsonLayer`](crate::JsonLayer) reports if
+/// [`log_internal_errors`](crate::JsonLayer::log_internal_errors) is enabled.
+///
+/// Each line is [one write](crate::writer#one-write-per-line). Writes happen on the thread emitting
+/// the event, so connecting and writing are bounded by a [timeout](Self::with_timeout).
+///
+/// When the writer is dropped, it makes a last attempt to connect and send the buffered lines,
+/// ignoring the backoff. Lines that still cannot be sent are lost.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use tracing_subscriber::prelude::*;
+/// # fn main() -> std::io::Result<()> {
+/// use json_subscriber::{writer::StreamWriter, JsonLayer};
+///
+/// let mut layer = JsonLayer::stdout().with_writer(StreamWriter::tcp("127.0.0.1:9000")?);
+/// layer.with_flattened_event().log_internal_errors(true);
+/// # tracing_subscriber::registry().with(layer);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StreamWriter {
+    address: Address,
+    state: Mutex<State>,
+    buffer_capacity: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    timeout: Duration,
+}
+
+impl StreamWriter {
+    /// The default number of lines buffered while disconnected.
+    pub const DEFAULT_BUFFER_CAPACITY: usize = 1024;
+
+    /// Creates a writer streaming lines to the given TCP address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be resolved. The connection itself is only
+    /// established when writing.
+    pub fn tcp(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let addresses = address.to_socket_addrs()?.collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "address resolved to nothing",
+            ));
+        }
+        Ok(Self::with_address(Address::Tcp(addresses)))
+    }
+
+    /// Creates a writer streaming lines to the Unix stream socket at the given path. The
+    /// connection is only established when writing.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        Self::with_address(Address::Unix(path.into()))
+    }
+
+    fn with_address(address: Address) -> Self {
+        let initial_backoff = Duration::from_millis(100);
+        Self {
+            address,
+            state: Mutex::new(State {
+                stream: None,
+                buffer: VecDeque::new(),
+                backoff: initial_backoff,
+                next_attempt: Instant::now(),
+            }),
+            buffer_capacity: Self::DEFAULT_BUFFER_CAPACITY,
+            initial_backoff,
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the maximum number of lines buffered while disconnected. Defaults to
+    /// [`DEFAULT_BUFFER_CAPACITY`](Self::DEFAULT_BUFFER_CAPACITY).
+    #[must_use]
+    pub fn with_buffer_capacity(mut self, buffer_capacity: usize) -> Self {
+        self.buffer_capacity = buffer_capacity;
+        self
+    }
+
+    /// Sets the delay before the first reconnection attempt and the maximum delay it doubles up
+    /// to after each failure. Defaults to 100 milliseconds and 30 seconds.
+    #[must_use]
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .backoff = initial;
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Sets the timeout of connecting and of each write. Defaults to one second.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn connect(&self) -> io::Result<Stream> {
+        match &self.address {
+            Address::Tcp(addresses) => {
+                let mut last_error = None;
+                for address in addresses {
+                    match TcpStream::connect_timeout(address, self.timeout) {
+                        Ok(stream) => {
+                            stream.set_write_timeout(Some(self.timeout))?;
+                            return Ok(Stream::Tcp(stream));
+                        },
+                        Err(error) => last_error = Some(error),
+                    }
+                }
+                Err(last_error.unwrap_or_else(|| io::ErrorKind::NotConnected.into()))
+            },
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(self.timeout))?;
+                Ok(Stream::Unix(stream))
+            },
+        }
+    }
+
+    /// Connects if disconnected and the backoff has elapsed, then sends buffered lines. Returns
+    /// whether the buffer is empty and connected afterwards.
+    fn drain(&self, state: &mut State) -> bool {
+        if state.stream.is_none() {
+            if Instant::now() < state.next_attempt {
+                return false;
+            }
+            let Ok(stream) = self.connect() else {
+                self.disconnect(state);
+                return false;
+            };
+            state.stream = Some(stream);
+            state.backoff = self.initial_backoff;
+        }
+
+        while let Some(line) = state.buffer.front() {
+            let Some(stream) = &mut state.stream else {
+                return false;
+            };
+            if stream.write_all(line).is_err() {
+                self.disconnect(state);
+                return false;
+            }
+            state.buffer.pop_front();
+        }
+        true
+    }
+
+    fn disconnect(&self, state: &mut State) {
+        state.stream = None;
+        state.next_attempt = Instant::now() + state.backoff;
+        state.backoff = (state.backoff * 2).min(self.max_backoff);
+    }
+
+    /// Sends the bytes or buffers them while disconnected. Also used by the TCP transport of
+    /// [`SyslogWriter`](super::SyslogWriter).
+    pub(super) fn send(&self, line: &[u8]) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if self.drain(&mut state) {
+            if let Some(stream) = &mut state.stream {
+                if stream.write_all(line).is_ok() {
+                    return Ok(());
+                }
+            }
+            self.disconnect(&mut state);
+        }
+
+        state.buffer.push_back(line.to_vec());
+        if state.buffer.len() > self.buffer_capacity {
+            state.buffer.pop_front();
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!(
+                    "stream is disconnected and the buffer of {} lines is full, dropped the \
+                     oldest line",
+                    self.buffer_capacity,
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if !state.buffer.is_empty() {
+            state.next_attempt = Instant::now();
+            self.drain(&mut state);
+        }
+    }
+}
+
+impl io::Write for &StreamWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf)?;
+        Ok(buf.len())
+    }
+
+    /// Tries to send all buffered lines, connecting if the backoff has elapsed.
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.drain(&mut state) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("{} lines could not be sent", state.buffer.len()),
+            ))
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for StreamWriter {
+    type Writer = &'a StreamWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        time::Duration,
+    };
+
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::{registry, Layer};
+
+    use super::StreamWriter;
+    #[cfg(unix)]
+    use crate::tests::TempSocketPath;
+    use crate::JsonLayer;
+
+    #[test]
+    fn tcp_lines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let writer = StreamWriter::tcp(listener.local_addr().unwrap()).unwrap();
+        let mut layer = JsonLayer::stdout().with_writer(writer);
+        layer.with_flattened_event();
+
+        with_default(layer.with_subscriber(registry()), || {
+            tracing::info!("first");
+            tracing::info!(answer = 42, "second");
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let lines = BufReader::new(stream)
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(&line.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                serde_json::json!({ "message": "first" }),
+                serde_json::json!({ "message": "second", "answer": 42 }),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reconnect_and_drop() {
+        use std::os::unix::net::UnixListener;
+
+        let path = TempSocketPath::new();
+        let writer = StreamWriter::unix(&*path)
+            .with_buffer_capacity(2)
+            .with_backoff(Duration::ZERO, Duration::ZERO);
+
+        // Nothing is listening so lines are buffered and the oldest one is dropped.
+        (&writer).write_all(b"1\n").unwrap();
+        (&writer).write_all(b"2\n").unwrap();
+        let error = (&writer).write_all(b"3\n").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotConnected);
+        assert!((&writer).flush().is_err());
+
+        let listener = UnixListener::bind(&path).unwrap();
+        (&writer).write_all(b"4\n").unwrap();
+        drop(writer);
+
+        let (stream, _) = listener.accept().unwrap();
+        let lines = BufReader::new(stream)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, ["2", "3", "4"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn backoff() {
+        use std::os::unix::net::UnixListener;
+
+        let path = TempSocketPath::new();
+        let writer = StreamWriter::unix(&*path)
+            .with_backoff(Duration::from_secs(60), Duration::from_secs(60));
+
+        (&writer).write_all(b"1\n").unwrap();
+        // The listener exists now but the next attempt is only after the backoff.
+        let listener = UnixListener::bind(&path).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        (&writer).write_all(b"2\n").unwrap();
+        assert!(listener.accept().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn drain_on_drop() {
+        use std::os::unix::net::UnixListener;
+
+        let path = TempSocketPath::new();
+        let writer = StreamWriter::unix(&*path)
+            .with_backoff(Duration::from_secs(60), Duration::from_secs(60));
+
+        (&writer).write_all(b"1\n").unwrap();
+        let listener = UnixListener::bind(&path).unwrap();
+        // Dropping makes a last attempt to send the buffered lines despite the backoff.
+        drop(writer);
+
+        let (stream, _) = listener.accept().unwrap();
+        let lines = BufReader::new(stream)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, ["1"]);
+    }
+}
@@ -0,0 +1,412 @@
+use std::{
+    io,
+    net::{ToSocketAddrs, UdpSocket},
+    time::SystemTime,
+};
+#[cfg(unix)]
+use std::{os::unix::net::UnixDatagram, path::Path};
+
+use tracing_core::Metadata;
+use tracing_subscriber::fmt::MakeWriter;
+
+use super::StreamWriter;
+use crate::{level_format::syslog_severity, process, Rfc3339, TimestampFormat};
+
+/// Syslog facilities as defined in [RFC 5424], used to compute the priority of messages sent by
+/// [`SyslogWriter`].
+///
+/// [RFC 5424]: https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(missing_docs)]
+pub enum SyslogFacility {
+    Kernel = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Ntp = 12,
+    Audit = 13,
+    Alert = 14,
+    Clock = 15,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+#[derive(Debug)]
+enum Transport {
+    Udp(UdpSocket),
+    Tcp(StreamWriter),
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+}
+
+/// A [`MakeWriter`] wrapping each written line in an [RFC 5424] syslog frame.
+///
+/// Each message is [one write](crate::writer#one-write-per-line). The priority is computed from the
+/// facility and the level of the event, mapped to a syslog severity. The line is the message of
+/// the frame, so a JSON line can be parsed again by the receiver. Messages
+/// written without an event use the `INFO` severity.
+///
+/// Messages are sent over UDP, over TCP with octet-counting framing as described in [RFC 6587],
+/// or to a local Unix datagram socket such as `/dev/log`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use tracing_subscriber::prelude::*;
+/// # fn main() -> std::io::Result<()> {
+/// use json_subscriber::{
+///     writer::{SyslogFacility, SyslogWriter},
+///     JsonLayer,
+/// };
+///
+/// let writer = SyslogWriter::tcp("127.0.0.1:601")?
+///     .with_app_name("my-service")
+///     .with_facility(SyslogFacility::Local0);
+/// let mut layer = JsonLayer::stdout().with_writer(writer);
+/// layer.with_flattened_event();
+/// # tracing_subscriber::registry().with(layer);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [RFC 5424]: https://datatracker.ietf.org/doc/html/rfc5424
+/// [RFC 6587]: https://datatracker.ietf.org/doc/html/rfc6587#section-3.4.1
+#[derive(Debug)]
+pub struct SyslogWriter {
+    transport: Transport,
+    facility: SyslogFacility,
+    hostname: String,
+    app_name: String,
+    proc_id: String,
+}
+
+impl SyslogWriter {
+    /// Creates a writer sending messages as UDP datagrams to the given address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be resolved or a socket cannot be bound.
+    pub fn udp(address: impl ToSocketAddrs) -> io::Result<Self> {
+        super::connect_udp(address).map(|socket| Self::with_transport(Transport::Udp(socket)))
+    }
+
+    /// Creates a writer sending messages over a TCP connection to the given address, framed with
+    /// octet counting.
+    ///
+    /// The connection is established on the first write and re-established after it breaks, with
+    /// the same backoff and buffering of messages as a [`StreamWriter`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be resolved.
+    pub fn tcp(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let writer = StreamWriter::tcp(address)?;
+        Ok(Self::with_transport(Transport::Tcp(writer)))
+    }
+
+    /// Creates a writer sending messages to a Unix datagram socket at the given path, usually
+    /// `/dev/log`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be connected.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    pub fn unix(path: impl AsRef<Path>) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self::with_transport(Transport::Unix(socket)))
+    }
+
+    fn with_transport(transport: Transport) -> Self {
+        Self {
+            transport,
+            facility: SyslogFacility::default(),
+            hostname: header_field(process::hostname().as_deref().unwrap_or_default(), 255),
+            app_name: header_field(
+                process::executable_name().as_deref().unwrap_or_default(),
+                48,
+            ),
+            proc_id: std::process::id().to_string(),
+        }
+    }
+
+    /// Sets the facility used to compute the priority. Defaults to [`SyslogFacility::User`].
+    #[must_use]
+    pub fn with_facility(self, facility: SyslogFacility) -> Self {
+        Self { facility, ..self }
+    }
+
+    /// Sets the `HOSTNAME` of the frame. Defaults to the hostname of the machine.
+    ///
+    /// Characters that are not allowed are replaced with `_` and it is truncated to 255
+    /// characters.
+    #[must_use]
+    pub fn with_hostname(self, hostname: impl AsRef<str>) -> Self {
+        Self {
+            hostname: header_field(hostname.as_ref(), 255),
+            ..self
+        }
+    }
+
+    /// Sets the `APP-NAME` of the frame. Defaults to the name of the executable.
+    ///
+    /// Characters that are not allowed are replaced with `_` and it is truncated to 48
+    /// characters.
+    #[must_use]
+    pub fn with_app_name(self, app_name: impl AsRef<str>) -> Self {
+        Self {
+            app_name: header_field(app_name.as_ref(), 48),
+            ..self
+        }
+    }
+
+    fn frame(&self, severity: u8, message: &[u8]) -> Vec<u8> {
+        let message = super::strip_newline(message);
+
+        let mut timestamp = String::new();
+        _ = TimestampFormat::Rfc3339(Rfc3339::new()).write(&mut timestamp, SystemTime::now());
+        let timestamp = timestamp.trim_matches('"');
+
+        let header = format!(
+            "<{}>1 {timestamp} {} {} {} - - ",
+            self.facility as u8 * 8 + severity,
+            self.hostname,
+            self.app_name,
+            self.proc_id,
+        );
+
+        let mut frame = Vec::with_capacity(header.len() + message.len());
+        frame.extend_from_slice(header.as_bytes());
+        frame.extend_from_slice(message);
+        frame
+    }
+
+    fn send(&self, severity: u8, message: &[u8]) -> io::Result<()> {
+        let frame = self.frame(severity, message);
+        match &self.transport {
+            Transport::Udp(socket) => {
+                socket.send(&frame)?;
+            },
+            Transport::Tcp(writer) => {
+                let mut counted = format!("{} ", frame.len()).into_bytes();
+                counted.extend_from_slice(&frame);
+                writer.send(&counted)?;
+            },
+            #[cfg(unix)]
+            Transport::Unix(socket) => {
+                socket.send(&frame)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Converts a value into a header field consisting only of printable ASCII characters, using `-`
+/// for empty values.
+fn header_field(value: &str, max_len: usize) -> String {
+    let field = value
+        .chars()
+        .map(|char| if char.is_ascii_graphic() { char } else { '_' })
+        .take(max_len)
+        .collect::<String>();
+    if field.is_empty() {
+        "-".to_owned()
+    } else {
+        field
+    }
+}
+
+/// The [`io::Write`] implementation returned by [`SyslogWriter`]. It carries the severity of the
+/// event being written.
+#[derive(Debug)]
+pub struct SyslogEntry<'a> {
+    writer: &'a SyslogWriter,
+    severity: u8,
+}
+
+impl io::Write for SyslogEntry<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.send(self.severity, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for SyslogWriter {
+    type Writer = SyslogEntry<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        SyslogEntry {
+            writer: self,
+            severity: syslog_severity(tracing_core::Level::INFO),
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        SyslogEntry {
+            writer: self,
+            severity: syslog_severity(*meta.level()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read},
+        net::{TcpListener, UdpSocket},
+        time::{Duration, Instant},
+    };
+
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::{registry, Layer};
+
+    use super::{SyslogFacility, SyslogWriter};
+    use crate::JsonLayer;
+
+    fn produce(writer: SyslogWriter, producer: impl FnOnce()) {
+        let mut layer = JsonLayer::stdout().with_writer(writer);
+        layer.with_flattened_event();
+        with_default(layer.with_subscriber(registry()), producer);
+    }
+
+    /// Splits a frame into the header fields and the message.
+    fn parse(frame: &str) -> (Vec<&str>, serde_json::Value) {
+        let parts = frame.splitn(8, ' ').collect::<Vec<_>>();
+        let message = serde_json::from_str(parts[7]).unwrap();
+        (parts[..7].to_vec(), message)
+    }
+
+    #[test]
+    fn udp_frame() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let writer = SyslogWriter::udp(socket.local_addr().unwrap())
+            .unwrap()
+            .with_app_name("test app")
+            .with_hostname("host")
+            .with_facility(SyslogFacility::Local0);
+
+        produce(writer, || tracing::warn!(answer = 42, "hello syslog"));
+
+        let mut buf = vec![0; 65536];
+        let len = socket.recv(&mut buf).unwrap();
+        let frame = std::str::from_utf8(&buf[..len]).unwrap();
+        let (header, message) = parse(frame);
+
+        // local0 * 8 + warning
+        assert_eq!(header[0], "<132>1");
+        assert!(header[1].ends_with('Z'));
+        assert_eq!(header[2], "host");
+        assert_eq!(header[3], "test_app");
+        assert_eq!(header[4], std::process::id().to_string());
+        assert_eq!(header[5..], ["-", "-"]);
+        assert_eq!(
+            message,
+            serde_json::json!({ "message": "hello syslog", "answer": 42 })
+        );
+    }
+
+    #[test]
+    fn tcp_octet_counting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let writer = SyslogWriter::tcp(listener.local_addr().unwrap()).unwrap();
+
+        produce(writer, || {
+            tracing::error!("first");
+            tracing::debug!("second\nline");
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        for (priority, expected) in [("<11>1", "first"), ("<15>1", "second\nline")] {
+            let mut len = Vec::new();
+            reader.read_until(b' ', &mut len).unwrap();
+            let len = std::str::from_utf8(&len).unwrap().trim().parse().unwrap();
+            let mut frame = vec![0; len];
+            reader.read_exact(&mut frame).unwrap();
+            let (header, message) = parse(std::str::from_utf8(&frame).unwrap());
+            assert_eq!(header[0], priority);
+            assert_eq!(message["message"], expected);
+        }
+    }
+
+    #[test]
+    fn tcp_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let writer = SyslogWriter::tcp(listener.local_addr().unwrap()).unwrap();
+
+        produce(writer, || {
+            tracing::info!("first");
+            drop(listener.accept().unwrap());
+
+            // Writes fail once the closed connection is noticed and the writer connects again
+            // after the backoff.
+            listener.set_nonblocking(true).unwrap();
+            let deadline = Instant::now() + Duration::from_secs(5);
+            let stream = loop {
+                tracing::info!("again");
+                if let Ok((stream, _)) = listener.accept() {
+                    break stream;
+                }
+                assert!(Instant::now() < deadline, "the writer did not reconnect");
+                std::thread::sleep(Duration::from_millis(20));
+            };
+            stream.set_nonblocking(false).unwrap();
+
+            let mut reader = BufReader::new(stream);
+            let mut len = Vec::new();
+            reader.read_until(b' ', &mut len).unwrap();
+            let len = std::str::from_utf8(&len).unwrap().trim().parse().unwrap();
+            let mut frame = vec![0; len];
+            reader.read_exact(&mut frame).unwrap();
+            let (_, message) = parse(std::str::from_utf8(&frame).unwrap());
+            assert_eq!(message["message"], "again");
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_datagram() {
+        use std::os::unix::net::UnixDatagram;
+
+        use crate::tests::TempSocketPath;
+
+        let path = TempSocketPath::new();
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let writer = SyslogWriter::unix(&path).unwrap().with_app_name("");
+
+        produce(writer, || tracing::info!("local"));
+
+        let mut buf = vec![0; 65536];
+        let len = socket.recv(&mut buf).unwrap();
+        let (header, message) = parse(std::str::from_utf8(&buf[..len]).unwrap());
+        assert_eq!(header[0], "<14>1");
+        assert_eq!(header[3], "-");
+        assert_eq!(message["message"], "local");
+    }
+}